@@ -0,0 +1,60 @@
+//! Recovery of a stuck I2C bus.
+//!
+//! A slave that was interrupted in the middle of a read (a reset of the Uno, a glitch on
+//! the sensor cable) keeps holding SDA low until it was clocked through the rest of its
+//! byte, and the TWI can't start another transfer until then. SCL is toggled by hand to
+//! get it there.
+
+use arduino_hal::pac::{PORTC, TWI};
+use arduino_hal::I2c;
+
+/// SDA is A4 (PC4)
+const SDA: u8 = 1 << 4;
+/// SCL is A5 (PC5)
+const SCL: u8 = 1 << 5;
+/// Half of a 50kHz clock period
+const HALF_PERIOD_US: u32 = 10;
+
+/// Clocks a slave holding SDA low out of its transfer and ends it with a STOP condition
+/// The TWI is disabled meanwhile and enabled again afterwards, keeping its bit rate
+/// param bus: The bus, which owns the TWI and the SDA and SCL pins, borrowed so no transfer runs meanwhile
+pub fn recover_bus(_bus: &mut I2c) {
+    // The borrowed bus owns the TWI and both lines, the rest of port C is left alone
+    let (twi, port) = unsafe { (&*TWI::ptr(), &*PORTC::ptr()) };
+    twi.twcr.write(|w| unsafe { w.bits(0) });
+
+    // A line is pulled low by making it an output driving 0, and released by making it
+    // an input again, where the internal pull-ups (and any external ones) take it high.
+    // The output is never high while it is driven, so a slave holding the line low is safe.
+    let pull = |lines: u8| {
+        port.portc.modify(|r, w| unsafe { w.bits(r.bits() & !lines) });
+        port.ddrc.modify(|r, w| unsafe { w.bits(r.bits() | lines) });
+    };
+    let release = |lines: u8| {
+        port.ddrc.modify(|r, w| unsafe { w.bits(r.bits() & !lines) });
+        port.portc.modify(|r, w| unsafe { w.bits(r.bits() | lines) });
+    };
+    release(SDA | SCL);
+
+    for _ in 0..9 {
+        if port.pinc.read().bits() & SDA != 0 {
+            break;
+        }
+        pull(SCL);
+        arduino_hal::delay_us(HALF_PERIOD_US);
+        release(SCL);
+        arduino_hal::delay_us(HALF_PERIOD_US);
+    }
+
+    // STOP: SDA rises while SCL is high
+    pull(SCL);
+    pull(SDA);
+    arduino_hal::delay_us(HALF_PERIOD_US);
+    release(SCL);
+    arduino_hal::delay_us(HALF_PERIOD_US);
+    release(SDA);
+    arduino_hal::delay_us(HALF_PERIOD_US);
+
+    // Both lines are back to the pull-up inputs the TWI expects
+    twi.twcr.write(|w| w.twen().set_bit());
+}
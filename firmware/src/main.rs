@@ -0,0 +1,313 @@
+#![no_std]
+#![no_main]
+
+extern crate panic_halt;
+
+mod i2c;
+mod millis;
+mod servo;
+mod smoke;
+
+use arduino_hal::hal::port::Dynamic;
+use arduino_hal::port::mode::{OpenDrain, Output};
+use arduino_hal::port::Pin;
+use arduino_hal::{pins, Delay, Eeprom, I2c, Peripherals};
+#[cfg(feature = "servo-vent")]
+use arduino_hal::Adc;
+use bme680::{Bme680, DesiredSensorSettings, I2CAddress, IIRFilterSize, OversamplingSetting, PowerMode, SensorSettings, SettingsBuilder};
+use core::cell::RefCell;
+use core::time::Duration;
+use greenhouse_core::bus::SharedI2c;
+use greenhouse_core::climate::ClimateData;
+use greenhouse_core::controller::{Greenhouse, Hardware};
+use greenhouse_core::hal::{ClimateSensor, Display, Storage};
+use greenhouse_core::rtc::Ds3231;
+use lcd1602_driver::command::{DataWidth, State};
+use lcd1602_driver::lcd;
+use lcd1602_driver::lcd::{Basic, Ext, Lcd};
+use lcd1602_driver::sender::ParallelSender;
+use millis::{millis_init, MillisTimer};
+use servo::VentServo;
+use smoke::SmokeDetector;
+
+// How to flash arduino: https://github.com/creativcoder/rust-arduino-blink
+/// Pin out for our project
+///
+/// LCD 1602: # Character Display
+///     VSS: GND
+///     VDD: 5V
+///     V0: 3.3V (Contrast)
+///     RS: P2
+///     RW: GND
+///     E: P3
+///     A: 5V
+///     K: GND
+///     D4: P4
+///     D5: P5
+///     D6: P6
+///     D7: P7
+///
+/// BME680: # Temperature, Humidity, and Air Pressure Sensor
+///     Vin: 5V
+///     GND: GND
+///     SCK: A5
+///     SDI: A4
+///
+/// DS3231 or DS1307: # Real-time clock (optional, shares the I2C bus with the BME680)
+///     VCC: 5V
+///     GND: GND
+///     SCL: A5
+///     SDA: A4
+///
+/// Buzzer: # Audial alert
+///     +: P9 (PWM)
+///     -: GND
+///
+/// Smoke Detector: # Enables Sprinklers upon Smoke Detection (pin change interrupt PCINT0)
+///     +: P8
+///
+/// Button Up: # Goes Up Screen/Element
+///     +: 3.3V
+///     -: A0
+///
+/// Button Down: # Goes Down Screen/Element
+///     +: 3.3V
+///     -: A1
+///
+/// Button Select: # Selects Element
+///     +: 3.3V
+///     -: A2
+///
+/// Sprinklers:
+///     +: P1
+///     -: GND
+///
+/// Roof Vent: # Opens when too hot
+///     +: A3 (D13 with the servo-vent feature)
+///     -: GND
+///
+/// Roof Vent Servo: # Only with the servo-vent feature
+///     Signal: P10 (PWM of Timer1)
+///     Feedback potentiometer wiper: A3
+///
+/// Heater: # Runs when too cold
+///     +: P10 (P11 with the servo-vent feature)
+///     -: GND
+///
+/// Exhaust Fan: # Helps the open vent when it gets even hotter
+///     +: P12
+///     -: GND
+
+static mut SENDER: Option<ParallelSender<Pin<Output, Dynamic>, Pin<OpenDrain, Dynamic>, Pin<Output, Dynamic>, 4>> = None;
+static mut DELAY: Option<Delay> = None;
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    // Set up
+    let dp = Peripherals::take().unwrap();
+    let twi = dp.TWI;
+    let eeprom = Eeprom::new(dp.EEPROM);
+    let pins = pins!(dp);
+
+    // Start the millisecond clock everything is scheduled by
+    millis_init(dp.TC0);
+
+    let delayer = Delay::new();
+    let i2c = RefCell::new(I2c::new(
+        twi,
+        pins.a4.into_pull_up_input(),
+        pins.a5.into_pull_up_input(),
+        50000,
+    ));
+
+    // Set up BME680, a sensor that doesn't answer yet is retried by the controller
+    let settings = SettingsBuilder::new()
+        .with_humidity_oversampling(OversamplingSetting::OS2x)
+        .with_pressure_oversampling(OversamplingSetting::OS4x)
+        .with_temperature_oversampling(OversamplingSetting::OS8x)
+        .with_temperature_filter(IIRFilterSize::Size3)
+        .with_gas_measurement(Duration::from_millis(1500), 320, 25)
+        .with_run_gas(true)
+        .build();
+
+    let sensor = Bme680Sensor::new(&i2c, settings);
+
+    // Set up real-time clock
+    let rtc = Ds3231::new(SharedI2c::new(&i2c));
+
+    // Set up LCD1602
+    unsafe {
+        SENDER = Some(ParallelSender::<Pin<Output, Dynamic>, Pin<OpenDrain, Dynamic>, Pin<Output, Dynamic>, 4>::new_4pin(
+            pins.d2.into_output().downgrade(),
+            pins.d0.into_output().downgrade(),
+            pins.d3.into_output().downgrade(),
+            pins.d4.into_opendrain().downgrade(),
+            pins.d5.into_opendrain().downgrade(),
+            pins.d6.into_opendrain().downgrade(),
+            pins.d7.into_opendrain().downgrade(),
+            None,
+        ));
+
+        DELAY = Some(delayer);
+    }
+
+    let lcd_config = lcd::Config::default().set_data_width(DataWidth::Bit4);
+    let lcd = Lcd::new(
+        unsafe { SENDER.as_mut().unwrap() },
+        unsafe { DELAY.as_mut().unwrap() },
+        lcd_config,
+        10,
+    );
+
+    // Set up button up
+    let up_button = pins.a0.into_pull_up_input().downgrade();
+
+    // Set up button down
+    let down_button = pins.a1.into_pull_up_input().downgrade();
+
+    // Set up button select
+    let select_button = pins.a2.into_pull_up_input().downgrade();
+
+    // Set up buzzer
+    let buzzer = pins.d9.into_output().downgrade();
+
+    // Set up smoke detector
+    let smoke_detector = SmokeDetector::new(pins.d8.into_pull_up_input(), &dp.EXINT);
+
+    // Set up sprinklers
+    let sprinklers = pins.d1.into_output().downgrade();
+
+    // Set up roof vent and heater
+    #[cfg(not(feature = "servo-vent"))]
+    let (roof_vent, vent_servo, heater) = (pins.a3.into_output().downgrade(), None::<VentServo>, pins.d10.into_output().downgrade());
+    // The servo needs the 16-bit Timer1, whose output on D10 takes the place of the heater
+    #[cfg(feature = "servo-vent")]
+    let (roof_vent, vent_servo, heater) = {
+        let mut adc = Adc::new(dp.ADC, Default::default());
+        let feedback = pins.a3.into_analog_input(&mut adc).into_channel();
+        let servo = VentServo::new(dp.TC1, pins.d10.into_output(), adc, feedback);
+        (pins.d13.into_output().downgrade(), Some(servo), pins.d11.into_output().downgrade())
+    };
+
+    // Set up exhaust fan
+    let exhaust_fan = pins.d12.into_output().downgrade();
+
+    let hardware = Hardware {
+        up_button,
+        down_button,
+        select_button,
+        smoke_detector,
+        buzzer,
+        sprinklers,
+        roof_vent,
+        heater,
+        exhaust_fan,
+        sensor,
+        lcd: LcdDisplay(lcd),
+        delay: Delay::new(),
+        storage: EepromStorage(eeprom),
+        rtc: Some(rtc),
+        timer: MillisTimer,
+        vent_servo,
+    };
+
+    // Enable interrupts globally so the millisecond clock starts ticking
+    // and the smoke detector is watched
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut greenhouse = Greenhouse::new(hardware);
+
+    // Main app loop
+    loop {
+        greenhouse.tick();
+    }
+}
+
+/// BME680 exposed through the ClimateSensor trait
+struct Bme680Sensor<'a> {
+    i2c: &'a RefCell<I2c>,
+    /// None until the sensor answered
+    bme: Option<Bme680<SharedI2c<'a, I2c>, Delay>>,
+    settings: (SensorSettings, DesiredSensorSettings),
+    delayer: Delay,
+}
+
+impl<'a> Bme680Sensor<'a> {
+    /// Sets up the sensor, if it answers
+    /// param i2c: The shared I2C bus
+    /// param settings: Oversampling, filter and gas heater settings
+    fn new(i2c: &'a RefCell<I2c>, settings: (SensorSettings, DesiredSensorSettings)) -> Self {
+        let mut sensor = Bme680Sensor { i2c, bme: None, settings, delayer: Delay::new() };
+        let _ = sensor.init();
+        sensor
+    }
+
+    /// Resets the sensor and applies the settings
+    fn init(&mut self) -> Result<(), ()> {
+        self.bme = None;
+        let mut bme = Bme680::init(SharedI2c::new(self.i2c), &mut self.delayer, I2CAddress::Primary).map_err(|_| ())?;
+        bme.set_sensor_settings(&mut self.delayer, self.settings.clone()).map_err(|_| ())?;
+        self.bme = Some(bme);
+        Ok(())
+    }
+}
+
+impl ClimateSensor for Bme680Sensor<'_> {
+    type Error = ();
+
+    fn prepare(&mut self) -> Result<(), ()> {
+        let bme = self.bme.as_mut().ok_or(())?;
+        bme.set_sensor_mode(&mut self.delayer, PowerMode::ForcedMode).map_err(|_| ())
+    }
+
+    fn measure(&mut self) -> Result<ClimateData, ()> {
+        let bme = self.bme.as_mut().ok_or(())?;
+        let (data, _) = bme.get_sensor_data(&mut self.delayer).map_err(|_| ())?;
+        Ok(ClimateData {
+            temperature_celsius: data.temperature_celsius(),
+            humidity_percent: data.humidity_percent(),
+            pressure_hpa: data.pressure_hpa(),
+            // Only valid once the gas heater reached its 320C
+            gas_resistance_ohm: (data.gas_valid() && data.heat_stable()).then(|| data.gas_resistance_ohm()),
+        })
+    }
+
+    fn recover(&mut self) -> Result<(), ()> {
+        i2c::recover_bus(&mut self.i2c.borrow_mut());
+        self.init()
+    }
+}
+
+/// LCD 1602 exposed through the Display trait
+struct LcdDisplay(Lcd<'static, 'static, ParallelSender<Pin<Output>, Pin<OpenDrain>, Pin<Output>, 4>, Delay<>>);
+
+impl Display for LcdDisplay {
+    fn clean_display(&mut self) {
+        self.0.clean_display();
+    }
+
+    fn set_cursor_pos(&mut self, pos: (u8, u8)) {
+        self.0.set_cursor_pos(pos);
+    }
+
+    fn write_str_to_cur(&mut self, line: &str) {
+        self.0.write_str_to_cur(line);
+    }
+
+    fn set_cursor_blink(&mut self, on: bool) {
+        self.0.set_cursor_blink_state(if on { State::On } else { State::Off });
+    }
+}
+
+/// On-chip EEPROM exposed through the Storage trait
+struct EepromStorage(Eeprom);
+
+impl Storage for EepromStorage {
+    fn read_byte(&self, address: u16) -> u8 {
+        self.0.read_byte(address)
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.0.write_byte(address, value);
+    }
+}
@@ -0,0 +1,61 @@
+//! Millisecond uptime counter driven by a TC0 compare match interrupt.
+//!
+//! Based on the avr-hal millis example: TC0 runs in CTC mode with a /64
+//! prescaler and a TOP of 249, which fires `TIMER0_COMPA` exactly once per
+//! millisecond on the 16MHz Uno.
+
+use core::cell::Cell;
+use greenhouse_core::hal::Monotonic;
+
+const PRESCALER: u32 = 64;
+const TIMER_COUNTS: u32 = 250;
+
+const MILLIS_INCREMENT: u32 = PRESCALER * TIMER_COUNTS / 16000;
+
+static MILLIS_COUNTER: avr_device::interrupt::Mutex<Cell<u32>> = avr_device::interrupt::Mutex::new(Cell::new(0));
+
+/// Starts counting milliseconds
+/// Interrupts still need to be enabled globally afterwards
+/// param tc0: The timer to dedicate to the counter
+pub fn millis_init(tc0: arduino_hal::pac::TC0) {
+    // Configure the timer for the above interval (in CTC mode)
+    // and enable its interrupt
+    tc0.tccr0a.write(|w| w.wgm0().ctc());
+    tc0.ocr0a.write(|w| w.bits(TIMER_COUNTS as u8 - 1));
+    tc0.tccr0b.write(|w| match PRESCALER {
+        8 => w.cs0().prescale_8(),
+        64 => w.cs0().prescale_64(),
+        256 => w.cs0().prescale_256(),
+        1024 => w.cs0().prescale_1024(),
+        _ => panic!(),
+    });
+    tc0.timsk0.write(|w| w.ocie0a().set_bit());
+
+    // Reset the global millisecond counter
+    avr_device::interrupt::free(|cs| {
+        MILLIS_COUNTER.borrow(cs).set(0);
+    });
+}
+
+#[avr_device::interrupt(atmega328p)]
+fn TIMER0_COMPA() {
+    avr_device::interrupt::free(|cs| {
+        let counter_cell = MILLIS_COUNTER.borrow(cs);
+        let counter = counter_cell.get();
+        counter_cell.set(counter.wrapping_add(MILLIS_INCREMENT));
+    })
+}
+
+/// Gets the milliseconds since `millis_init`
+pub fn millis() -> u32 {
+    avr_device::interrupt::free(|cs| MILLIS_COUNTER.borrow(cs).get())
+}
+
+/// The uptime counter exposed through the Monotonic trait
+pub struct MillisTimer;
+
+impl Monotonic for MillisTimer {
+    fn millis(&self) -> u32 {
+        millis()
+    }
+}
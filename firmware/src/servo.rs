@@ -0,0 +1,59 @@
+//! Roof vent servo on D10 with a feedback potentiometer on A3.
+//!
+//! Timer1 runs in 16-bit fast PWM mode with its TOP in ICR1 and a prescaler of 8, which
+//! gives a period of exactly 20ms (50Hz) and 0.5us per step of the duty. The usual
+//! 1ms - 2ms servo pulse is therefore covered by 2000 steps, far finer than the percent
+//! positions the controller asks for.
+
+use arduino_hal::adc::Channel;
+use arduino_hal::hal::port::PB2;
+use arduino_hal::pac::TC1;
+use arduino_hal::port::mode::Output;
+use arduino_hal::port::Pin;
+use arduino_hal::Adc;
+use greenhouse_core::hal::Servo;
+
+/// Steps of a 20ms period
+const PERIOD: u16 = 40_000;
+/// Duty of a 1ms pulse
+const SERVO_MIN: u16 = 2000;
+/// Duty of a 2ms pulse
+const SERVO_MAX: u16 = 4000;
+
+/// The vent servo exposed through the Servo trait
+pub struct VentServo {
+    timer: TC1,
+    /// Driven by the timer (OC1B), kept so nothing else can use the pin
+    _pwm: Pin<Output, PB2>,
+    adc: Adc,
+    feedback: Channel,
+}
+
+impl VentServo {
+    /// Starts sending servo pulses, closed until the first position is set
+    /// param timer: Timer1, which is used for nothing else
+    /// param pwm: D10, set up as output
+    /// param adc: The ADC the feedback is read with
+    /// param feedback: A3, set up as analog input
+    pub fn new(timer: TC1, pwm: Pin<Output, PB2>, adc: Adc, feedback: Channel) -> Self {
+        // Fast PWM with TOP in ICR1 (mode 14), OC1B is set at BOTTOM and cleared on compare match
+        timer.icr1.write(|w| unsafe { w.bits(PERIOD - 1) });
+        timer.ocr1b.write(|w| unsafe { w.bits(SERVO_MIN) });
+        timer.tccr1a.write(|w| w.wgm1().bits(0b10).com1b().match_clear());
+        timer.tccr1b.write(|w| w.wgm1().bits(0b11).cs1().prescale_8());
+        VentServo { timer, _pwm: pwm, adc, feedback }
+    }
+}
+
+impl Servo for VentServo {
+    fn set_position(&mut self, position: u8) {
+        let duty = SERVO_MIN + (SERVO_MAX - SERVO_MIN) / 100 * position.min(100) as u16;
+        self.timer.ocr1b.write(|w| unsafe { w.bits(duty) });
+    }
+
+    fn feedback(&mut self) -> Option<u8> {
+        // The potentiometer turns with the servo shaft, 0V to 5V across its range
+        let raw = self.adc.read_blocking(&self.feedback);
+        Some((raw as u32 * 100 / 1023) as u8)
+    }
+}
@@ -0,0 +1,44 @@
+//! Smoke detector on D8 with a pin change interrupt.
+//!
+//! A pulse from the detector is latched by the `PCINT0` interrupt, so it is seen by the
+//! controller even if it happens while the main loop is busy talking to the sensor or LCD.
+
+use arduino_hal::hal::port::PB0;
+use arduino_hal::port::mode::{Input, PullUp};
+use arduino_hal::port::Pin;
+use core::cell::Cell;
+use greenhouse_core::hal::SmokeSensor;
+
+static SMOKE_SEEN: avr_device::interrupt::Mutex<Cell<bool>> = avr_device::interrupt::Mutex::new(Cell::new(false));
+
+/// The smoke detector exposed through the SmokeSensor trait
+pub struct SmokeDetector(Pin<Input<PullUp>, PB0>);
+
+impl SmokeDetector {
+    /// Enables the pin change interrupt of D8 (PCINT0)
+    /// Interrupts still need to be enabled globally afterwards
+    /// param pin: D8
+    /// param exint: The external interrupt peripheral
+    pub fn new(pin: Pin<Input<PullUp>, PB0>, exint: &arduino_hal::pac::EXINT) -> Self {
+        // PCIE0 covers PCINT0-7 (port B); only listen to PCINT0, which is D8
+        exint.pcicr.write(|w| unsafe { w.bits(0b001) });
+        exint.pcmsk0.write(|w| unsafe { w.bits(0b001) });
+        SmokeDetector(pin)
+    }
+}
+
+impl SmokeSensor for SmokeDetector {
+    fn is_smoke_detected(&self) -> bool {
+        let seen = avr_device::interrupt::free(|cs| SMOKE_SEEN.borrow(cs).replace(false));
+        seen || self.0.is_high()
+    }
+}
+
+#[avr_device::interrupt(atmega328p)]
+fn PCINT0() {
+    // The interrupt fires on both edges, only the rising one means smoke
+    let portb = unsafe { &*arduino_hal::pac::PORTB::ptr() };
+    if portb.pinb.read().pb0().bit_is_set() {
+        avr_device::interrupt::free(|cs| SMOKE_SEEN.borrow(cs).set(true));
+    }
+}
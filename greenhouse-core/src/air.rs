@@ -0,0 +1,145 @@
+//! Indoor air quality from the gas resistance of the BME680
+//!
+//! Volatile compounds (fungicide fumes, solvents, the gases that build up with CO2 in a
+//! closed greenhouse) lower the resistance of the heated metal oxide layer. The absolute
+//! value differs from sensor to sensor and drifts, so it is compared to a baseline that
+//! is learned in clean air: the first minutes after boot, then slowly following the
+//! cleanest air seen. Humidity also lowers the resistance, so a quarter of the index
+//! comes from the distance to the humidity the greenhouse is kept at.
+//!
+//! The index follows the 0 (excellent) to 500 (extremely polluted) scale of Bosch.
+
+/// Polls spent learning the baseline after boot, the gas heater needs a few minutes to settle
+pub const BURN_IN_SAMPLES: u16 = 300;
+/// Share of the index given to humidity, in percent
+const HUMIDITY_WEIGHT: u32 = 25;
+/// How quickly the baseline follows cleaner air (1 / N of the difference per poll)
+const BASELINE_RISE: u32 = 16;
+/// How quickly the baseline follows the sensor drifting down (1 / N per poll)
+const BASELINE_FALL: u32 = 4096;
+/// Highest index
+pub const IAQ_MAX: u16 = 500;
+/// Improvement of the index needed to end ventilation or an alert
+pub const IAQ_DEADBAND: u16 = 25;
+
+/// Learns the clean air baseline and rates readings against it
+#[derive(Default)]
+pub struct AirQuality {
+    /// Gas resistance of clean air in Ohm, 0 before the first reading
+    baseline: u32,
+    /// Valid readings seen since the calibration started
+    samples: u16,
+}
+
+impl AirQuality {
+    /// Feeds a reading to the baseline and rates it
+    /// param gas: Gas resistance in Ohm, None if the heater didn't reach its temperature
+    /// param humidity: Humidity in tenths of a percent
+    /// param target: The humidity the greenhouse is kept at, in tenths of a percent
+    /// returns the index, None while calibrating or without a valid gas reading
+    pub fn update(&mut self, gas: Option<u32>, humidity: u16, target: u16) -> Option<u16> {
+        let gas = gas.filter(|gas| *gas > 0)?;
+        if self.samples == 0 {
+            self.baseline = gas;
+        } else if gas > self.baseline || self.samples < BURN_IN_SAMPLES {
+            self.baseline = follow(self.baseline, gas, BASELINE_RISE);
+        } else {
+            self.baseline = follow(self.baseline, gas, BASELINE_FALL);
+        }
+
+        if self.samples < BURN_IN_SAMPLES {
+            self.samples += 1;
+            return None;
+        }
+        Some(index(gas, self.baseline, humidity, target))
+    }
+}
+
+/// Moves a value part of the way towards a target
+/// param value: The value
+/// param target: The target
+/// param divisor: The value moves 1 / divisor of the distance, at least 1
+fn follow(value: u32, target: u32, divisor: u32) -> u32 {
+    let step = (value.abs_diff(target) / divisor).max(1);
+    if target > value { value + step.min(target - value) } else { value - step.min(value - target) }
+}
+
+/// Rates a reading against the baseline
+/// param gas: Gas resistance in Ohm
+/// param baseline: Gas resistance of clean air in Ohm
+/// param humidity: Humidity in tenths of a percent
+/// param target: The humidity the greenhouse is kept at, in tenths of a percent
+/// returns the index from 0 (excellent) to 500 (extremely polluted)
+pub fn index(gas: u32, baseline: u32, humidity: u16, target: u16) -> u16 {
+    let (humidity, target) = (humidity.min(1000) as u32, target.clamp(1, 999) as u32);
+    // Both scores are in hundredths, 100 * 100 is perfect air
+    let humidity_score = if humidity > target {
+        (1000 - humidity) * HUMIDITY_WEIGHT * 100 / (1000 - target)
+    } else {
+        humidity * HUMIDITY_WEIGHT * 100 / target
+    };
+    let gas_weight = 100 - HUMIDITY_WEIGHT;
+    let gas_score = if gas < baseline { (gas as u64 * gas_weight as u64 * 100 / baseline as u64) as u32 } else { gas_weight * 100 };
+    let score = humidity_score + gas_score;
+    ((100 * 100 - score.min(100 * 100)) * IAQ_MAX as u32 / (100 * 100)) as u16
+}
+
+/// param iaq: The index
+/// returns the rating shown on the LCD
+pub fn label(iaq: u16) -> &'static str {
+    match iaq {
+        0..=50 => "Excellent",
+        51..=100 => "Good",
+        101..=150 => "Light",
+        151..=200 => "Moderate",
+        201..=250 => "Heavy",
+        251..=350 => "Severe",
+        _ => "Extreme",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calibrated(gas: u32) -> AirQuality {
+        let mut air = AirQuality::default();
+        for _ in 0..BURN_IN_SAMPLES {
+            assert_eq!(air.update(Some(gas), 650, 650), None);
+        }
+        air
+    }
+
+    #[test]
+    fn index_is_available_after_the_burn_in() {
+        let mut air = AirQuality::default();
+        assert_eq!(air.update(None, 650, 650), None);
+        let mut air = calibrated(50_000);
+        assert_eq!(air.update(Some(50_000), 650, 650), Some(0));
+    }
+
+    #[test]
+    fn lower_resistance_means_worse_air() {
+        // Half the clean air resistance costs half of the gas score
+        assert_eq!(index(25_000, 50_000, 650, 650), 187);
+        assert_eq!(index(5_000, 50_000, 650, 650), 337);
+        // Dry or wet air costs up to a quarter
+        assert_eq!(index(50_000, 50_000, 1000, 650), 125);
+        assert_eq!(index(50_000, 50_000, 325, 650), 62);
+        assert_eq!(label(187), "Moderate");
+    }
+
+    #[test]
+    fn baseline_follows_cleaner_air_quickly_and_drift_slowly() {
+        let mut air = calibrated(40_000);
+        for _ in 0..100 {
+            air.update(Some(60_000), 650, 650);
+        }
+        assert_eq!(air.update(Some(60_000), 650, 650), Some(0));
+        // Fumes don't become the new normal within minutes
+        for _ in 0..300 {
+            air.update(Some(20_000), 650, 650);
+        }
+        assert!(air.update(Some(20_000), 650, 650).unwrap() > 200);
+    }
+}
@@ -0,0 +1,161 @@
+//! Arbitration between the rules driving an actuator
+//!
+//! Every rule (fire suppression, manual override, watering schedule, climate thresholds)
+//! keeps its own request for an actuator instead of switching it directly. Once per tick
+//! the request with the highest priority decides the state; without any request the
+//! actuator is off. This way a rule that has nothing to say can't undo another one.
+
+use crate::vent::VentPosition;
+
+/// An actuator driven by the rules
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Output {
+    RoofVent,
+    Sprinklers,
+    Heater,
+    ExhaustFan,
+}
+
+/// Every actuator driven by the rules
+pub const OUTPUTS: [Output; 4] = [Output::RoofVent, Output::Sprinklers, Output::Heater, Output::ExhaustFan];
+
+impl Output {
+    /// returns the name shown on the LCD
+    pub fn label(&self) -> &'static str {
+        match self {
+            Output::RoofVent => "Vent",
+            Output::Sprinklers => "Water",
+            Output::Heater => "Heat",
+            Output::ExhaustFan => "Fan",
+        }
+    }
+}
+
+/// A rule that can request an actuator state, from lowest to highest priority
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Reason {
+    Temperature,
+    Humidity,
+    AirQuality,
+    /// The safe state held while the climate sensor is faulty
+    Fault,
+    Schedule,
+    Manual,
+    Fire,
+}
+
+/// Every rule, in order of priority
+const REASONS: [Reason; 7] = [Reason::Temperature, Reason::Humidity, Reason::AirQuality, Reason::Fault, Reason::Schedule, Reason::Manual, Reason::Fire];
+
+impl Reason {
+    /// returns the name shown on the LCD
+    pub fn label(&self) -> &'static str {
+        match self {
+            Reason::Temperature => "Temp",
+            Reason::Humidity => "Humid",
+            Reason::AirQuality => "Air",
+            Reason::Fault => "Fault",
+            Reason::Schedule => "Sched",
+            Reason::Manual => "Manual",
+            Reason::Fire => "Fire",
+        }
+    }
+}
+
+/// The outcome of the arbitration
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Decision {
+    pub on: bool,
+    /// The winning rule, None if no rule requested anything
+    pub reason: Option<Reason>,
+}
+
+/// The requests of every rule for one actuator
+#[derive(Default)]
+pub struct Arbiter {
+    requests: [Option<bool>; REASONS.len()],
+}
+
+impl Arbiter {
+    /// Replaces the request of a rule
+    /// param reason: The rule
+    /// param request: The requested state, None to withdraw the request
+    pub fn set(&mut self, reason: Reason, request: Option<bool>) {
+        self.requests[reason as usize] = request;
+    }
+
+    /// param reason: The rule
+    /// returns the current request of a rule
+    pub fn get(&self, reason: Reason) -> Option<bool> {
+        self.requests[reason as usize]
+    }
+
+    /// Picks the request with the highest priority
+    /// returns the state of the actuator and why
+    pub fn resolve(&self) -> Decision {
+        let winner = self.requests.iter().enumerate().rev().find_map(|(index, request)| request.map(|on| (index, on)));
+        match winner {
+            Some((index, on)) => Decision { on, reason: Some(REASONS[index]) },
+            None => Decision::default(),
+        }
+    }
+}
+
+/// The state of every actuator and the rule behind it, for display
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Outputs {
+    decisions: [Decision; OUTPUTS.len()],
+    /// Position of the vent servo, None without one
+    pub vent: Option<VentPosition>,
+    /// Output of the PID controller in percent, None while it is off
+    pub pid: Option<u8>,
+    /// Oscillations seen by the running auto-tune, None while not tuning
+    pub tuning: Option<u8>,
+}
+
+impl Outputs {
+    /// param output: The actuator
+    /// returns the state of the actuator and why
+    pub fn get(&self, output: Output) -> Decision {
+        self.decisions[output as usize]
+    }
+
+    /// param output: The actuator
+    /// param decision: The state of the actuator and why
+    pub fn set(&mut self, output: Output, decision: Decision) {
+        self.decisions[output as usize] = decision;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actuators_are_off_without_requests() {
+        assert_eq!(Arbiter::default().resolve(), Decision { on: false, reason: None });
+    }
+
+    #[test]
+    fn humidity_misting_is_not_undone_by_the_schedule() {
+        let mut sprinklers = Arbiter::default();
+        sprinklers.set(Reason::Humidity, Some(true));
+        // Outside the watering window the schedule has nothing to say
+        sprinklers.set(Reason::Schedule, None);
+        assert_eq!(sprinklers.resolve(), Decision { on: true, reason: Some(Reason::Humidity) });
+    }
+
+    #[test]
+    fn higher_priorities_win() {
+        let mut vent = Arbiter::default();
+        vent.set(Reason::Temperature, Some(true));
+        vent.set(Reason::Manual, Some(false));
+        assert_eq!(vent.resolve(), Decision { on: false, reason: Some(Reason::Manual) });
+        vent.set(Reason::Fire, Some(false));
+        vent.set(Reason::Manual, Some(true));
+        assert_eq!(vent.resolve(), Decision { on: false, reason: Some(Reason::Fire) });
+        vent.set(Reason::Fire, None);
+        vent.set(Reason::Manual, None);
+        assert_eq!(vent.resolve().reason, Some(Reason::Temperature));
+    }
+}
@@ -0,0 +1,38 @@
+//! Sharing a single I2C bus between several drivers
+
+use core::cell::RefCell;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+/// A handle to an I2C bus that is shared through a RefCell
+/// Every driver on the bus gets its own handle
+pub struct SharedI2c<'a, I2C>(&'a RefCell<I2C>);
+
+impl<'a, I2C> SharedI2c<'a, I2C> {
+    pub fn new(bus: &'a RefCell<I2C>) -> Self {
+        SharedI2c(bus)
+    }
+}
+
+impl<I2C: Read> Read for SharedI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().read(address, buffer)
+    }
+}
+
+impl<I2C: Write> Write for SharedI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().write(address, bytes)
+    }
+}
+
+impl<I2C: WriteRead> WriteRead for SharedI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().write_read(address, bytes, buffer)
+    }
+}
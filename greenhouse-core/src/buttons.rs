@@ -0,0 +1,203 @@
+//! Debounced button events
+//!
+//! The raw levels of the three buttons are sampled once per tick and turned into events.
+//! UP and DOWN pressed together form a chord, which replaces the events of both buttons
+//! until they are released. To tell a chord from a single press, the press of UP or DOWN
+//! is only reported once CHORD_MS passed without the other button joining in.
+
+use heapless::Vec;
+
+/// A level has to be stable for this long to count
+pub const DEBOUNCE_MS: u32 = 30;
+/// Time for the second button of a chord to be pressed
+pub const CHORD_MS: u32 = 80;
+/// Time a button has to be held for a long press
+pub const LONG_PRESS_MS: u32 = 800;
+/// Time between repeats after a long press
+pub const REPEAT_MS: u32 = 250;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ButtonId {
+    Up,
+    Down,
+    Select,
+}
+
+const IDS: [ButtonId; 3] = [ButtonId::Up, ButtonId::Down, ButtonId::Select];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+    Press(ButtonId),
+    Release(ButtonId),
+    /// The button was held for LONG_PRESS_MS
+    LongPress(ButtonId),
+    /// Sent every REPEAT_MS after a long press while the button is held
+    Repeat(ButtonId),
+    /// UP and DOWN pressed together
+    Chord,
+}
+
+/// Events produced by a single update
+pub type Events = Vec<Event, 8>;
+
+#[derive(Clone, Copy, Default)]
+struct State {
+    /// Level of the previous sample
+    raw: bool,
+    /// When the raw level last changed
+    changed_at: u32,
+    /// Debounced level
+    pressed: bool,
+    /// When the debounced press began
+    pressed_at: u32,
+    /// The press has not been reported yet
+    pending: bool,
+    long_pressed: bool,
+    last_repeat: u32,
+    /// Part of a chord, so the button sends no events of its own until released
+    in_chord: bool,
+}
+
+/// Turns button levels into events
+#[derive(Default)]
+pub struct Buttons {
+    states: [State; 3],
+}
+
+impl Buttons {
+    /// Samples the buttons
+    /// param levels: If Up, Down and Select are pressed
+    /// param now: Milliseconds since boot
+    /// returns the events that happened since the previous update
+    pub fn update(&mut self, levels: [bool; 3], now: u32) -> Events {
+        let mut events = Events::new();
+
+        // Debounce and detect releases
+        for (index, state) in self.states.iter_mut().enumerate() {
+            if levels[index] != state.raw {
+                state.raw = levels[index];
+                state.changed_at = now;
+            }
+            if state.raw == state.pressed || now.wrapping_sub(state.changed_at) < DEBOUNCE_MS {
+                continue;
+            }
+
+            state.pressed = state.raw;
+            if state.pressed {
+                *state = State { pressed_at: now, pending: true, long_pressed: false, in_chord: false, ..*state };
+            } else {
+                if !state.in_chord {
+                    // Short taps are reported even if they ended within the chord window
+                    if state.pending {
+                        let _ = events.push(Event::Press(IDS[index]));
+                    }
+                    let _ = events.push(Event::Release(IDS[index]));
+                }
+                state.pending = false;
+                state.in_chord = false;
+            }
+        }
+
+        // Detect chords
+        let [up, down, _] = &mut self.states;
+        if up.pressed && down.pressed && !up.in_chord && !down.in_chord {
+            for state in [up, down] {
+                state.in_chord = true;
+                state.pending = false;
+            }
+            let _ = events.push(Event::Chord);
+        }
+
+        // Report presses, long presses and repeats
+        for (index, state) in self.states.iter_mut().enumerate() {
+            if !state.pressed || state.in_chord {
+                continue;
+            }
+            let held = now.wrapping_sub(state.pressed_at);
+            if state.pending {
+                // Select is not part of any chord
+                if IDS[index] == ButtonId::Select || held >= CHORD_MS {
+                    state.pending = false;
+                    let _ = events.push(Event::Press(IDS[index]));
+                }
+            } else if !state.long_pressed {
+                if held >= LONG_PRESS_MS {
+                    state.long_pressed = true;
+                    state.last_repeat = now;
+                    let _ = events.push(Event::LongPress(IDS[index]));
+                }
+            } else if now.wrapping_sub(state.last_repeat) >= REPEAT_MS {
+                state.last_repeat = now;
+                let _ = events.push(Event::Repeat(IDS[index]));
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UP: [bool; 3] = [true, false, false];
+    const SELECT: [bool; 3] = [false, false, true];
+    const CHORD: [bool; 3] = [true, true, false];
+    const NONE: [bool; 3] = [false; 3];
+
+    /// Samples the same levels every 10ms
+    fn hold(buttons: &mut Buttons, levels: [bool; 3], from: u32, to: u32) -> Events {
+        let mut events = Events::new();
+        for now in (from..to).step_by(10) {
+            for event in buttons.update(levels, now) {
+                events.push(event).unwrap();
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn bounces_are_ignored() {
+        let mut buttons = Buttons::default();
+        for (now, levels) in [(0, SELECT), (10, NONE), (20, SELECT), (30, NONE), (40, NONE)] {
+            assert!(buttons.update(levels, now).is_empty());
+        }
+        assert!(hold(&mut buttons, NONE, 50, 200).is_empty());
+    }
+
+    #[test]
+    fn select_is_reported_on_press_and_release() {
+        let mut buttons = Buttons::default();
+        assert_eq!(hold(&mut buttons, SELECT, 0, 100), [Event::Press(ButtonId::Select)]);
+        assert_eq!(hold(&mut buttons, NONE, 100, 200), [Event::Release(ButtonId::Select)]);
+    }
+
+    #[test]
+    fn holding_sends_a_long_press_then_repeats() {
+        let mut buttons = Buttons::default();
+        let events = hold(&mut buttons, UP, 0, 30 + LONG_PRESS_MS + 2 * REPEAT_MS + 10);
+        assert_eq!(events, [
+            Event::Press(ButtonId::Up),
+            Event::LongPress(ButtonId::Up),
+            Event::Repeat(ButtonId::Up),
+            Event::Repeat(ButtonId::Up),
+        ]);
+    }
+
+    #[test]
+    fn quick_taps_are_not_lost() {
+        let mut buttons = Buttons::default();
+        assert!(hold(&mut buttons, UP, 0, 60).is_empty());
+        assert_eq!(hold(&mut buttons, NONE, 60, 200), [Event::Press(ButtonId::Up), Event::Release(ButtonId::Up)]);
+    }
+
+    #[test]
+    fn up_and_down_together_form_a_chord() {
+        let mut buttons = Buttons::default();
+        hold(&mut buttons, UP, 0, 40);
+        assert_eq!(hold(&mut buttons, CHORD, 40, 30 + LONG_PRESS_MS * 2), [Event::Chord]);
+        // Releasing one button at a time doesn't send anything either
+        assert!(hold(&mut buttons, [false, true, false], 1700, 1800).is_empty());
+        assert!(hold(&mut buttons, NONE, 1800, 1900).is_empty());
+    }
+}
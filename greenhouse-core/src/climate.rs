@@ -0,0 +1,168 @@
+/// A single measurement as reported by the driver of the environmental sensor
+/// Converted into a Reading before anything else looks at it
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct ClimateData {
+    pub temperature_celsius: f32,
+    pub humidity_percent: f32,
+    pub pressure_hpa: f32,
+    /// None if the gas heater didn't reach its temperature
+    pub gas_resistance_ohm: Option<u32>,
+}
+
+/// Temperature in tenths of a degree Celsius
+/// Every threshold and measurement is kept in this unit, so negative values and
+/// fractions survive; it is only converted for display and editing
+pub type Tenths = i16;
+
+/// The unit temperatures are shown and edited in
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TemperatureUnit {
+    Fahrenheit,
+    Celsius,
+}
+
+/// Divides and rounds to the nearest integer, halves away from zero
+/// param value: The dividend
+/// param divisor: The divisor, positive
+fn div_round(value: i32, divisor: i32) -> i32 {
+    if value < 0 { (value - divisor / 2) / divisor } else { (value + divisor / 2) / divisor }
+}
+
+impl TemperatureUnit {
+    /// param value: The stored value
+    /// returns the unit, Fahrenheit for unknown values
+    pub fn from_u8(value: u8) -> Self {
+        if value == 1 { TemperatureUnit::Celsius } else { TemperatureUnit::Fahrenheit }
+    }
+
+    /// returns the symbol shown on the LCD
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Fahrenheit => "F",
+            TemperatureUnit::Celsius => "C",
+        }
+    }
+
+    /// Converts a temperature for display
+    /// param tenths: Temperature in tenths of a degree Celsius
+    /// returns the temperature in whole degrees of this unit, rounded
+    pub fn to_degrees(&self, tenths: Tenths) -> i16 {
+        match self {
+            TemperatureUnit::Fahrenheit => div_round(tenths as i32 * 9 + 1600, 50) as i16,
+            TemperatureUnit::Celsius => div_round(tenths as i32, 10) as i16,
+        }
+    }
+
+    /// Converts a temperature for display with one decimal
+    /// param tenths: Temperature in tenths of a degree Celsius
+    /// returns the temperature in tenths of a degree of this unit, rounded
+    pub fn to_tenths(&self, tenths: Tenths) -> i16 {
+        match self {
+            TemperatureUnit::Fahrenheit => (div_round(tenths as i32 * 9, 5) + 320) as i16,
+            TemperatureUnit::Celsius => tenths,
+        }
+    }
+
+    /// Converts an edited temperature back
+    /// param degrees: Temperature in whole degrees of this unit
+    /// returns the temperature in tenths of a degree Celsius
+    pub fn from_degrees(&self, degrees: i16) -> Tenths {
+        match self {
+            TemperatureUnit::Fahrenheit => div_round((degrees as i32 - 32) * 50, 9) as Tenths,
+            TemperatureUnit::Celsius => degrees * 10,
+        }
+    }
+
+    /// Converts a temperature difference (deadband, band) for display
+    /// param tenths: Difference in tenths of a degree Celsius
+    /// returns the difference in whole degrees of this unit, rounded
+    pub fn to_difference(&self, tenths: Tenths) -> i16 {
+        match self {
+            TemperatureUnit::Fahrenheit => div_round(tenths as i32 * 9, 50) as i16,
+            TemperatureUnit::Celsius => div_round(tenths as i32, 10) as i16,
+        }
+    }
+
+    /// Converts an edited temperature difference back
+    /// param degrees: Difference in whole degrees of this unit
+    /// returns the difference in tenths of a degree Celsius
+    pub fn from_difference(&self, degrees: i16) -> Tenths {
+        match self {
+            TemperatureUnit::Fahrenheit => div_round(degrees as i32 * 50, 9) as Tenths,
+            TemperatureUnit::Celsius => degrees * 10,
+        }
+    }
+}
+
+/// Rounds to the nearest integer, halves away from zero
+/// Casting alone truncates towards zero, which would bunch up readings around 0
+/// param value: The value
+fn round(value: f32) -> i32 {
+    (if value < 0. { value - 0.5 } else { value + 0.5 }) as i32
+}
+
+/// A measurement in fixed point, converted from the sensor once per poll
+/// Display, control and logging all use this, so they agree on every value
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Reading {
+    /// Tenths of a degree Celsius
+    pub temperature: Tenths,
+    /// Tenths of a percent relative humidity
+    pub humidity: u16,
+    /// Pascal
+    pub pressure: u32,
+    /// Gas resistance in Ohm, None without a valid gas measurement
+    pub gas: Option<u32>,
+    /// Indoor air quality index (see air), None while the baseline is learned
+    pub iaq: Option<u16>,
+}
+
+impl From<&ClimateData> for Reading {
+    fn from(data: &ClimateData) -> Self {
+        Reading {
+            temperature: round(data.temperature_celsius * 10.).clamp(i16::MIN as i32, i16::MAX as i32) as Tenths,
+            humidity: round(data.humidity_percent * 10.).clamp(0, 1000) as u16,
+            pressure: round(data.pressure_hpa * 100.).max(0) as u32,
+            gas: data.gas_resistance_ohm,
+            // Needs the baseline kept by the controller
+            iaq: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_fahrenheit() {
+        let fahrenheit = TemperatureUnit::Fahrenheit;
+        assert_eq!(fahrenheit.to_degrees(0), 32);
+        assert_eq!(fahrenheit.to_degrees(250), 77);
+        assert_eq!(fahrenheit.to_degrees(-400), -40);
+        // -5.2C is 22.6F
+        assert_eq!(fahrenheit.to_degrees(-52), 23);
+        assert_eq!(fahrenheit.to_difference(11), 2);
+    }
+
+    #[test]
+    fn edited_degrees_convert_back_exactly() {
+        for unit in [TemperatureUnit::Fahrenheit, TemperatureUnit::Celsius] {
+            for degrees in -40..140 {
+                assert_eq!(unit.to_degrees(unit.from_degrees(degrees)), degrees);
+                assert_eq!(unit.to_difference(unit.from_difference(degrees)), degrees);
+            }
+        }
+    }
+
+    #[test]
+    fn readings_are_rounded_to_tenths() {
+        let frost = Reading::from(&ClimateData { temperature_celsius: -5.04, humidity_percent: 65.36, pressure_hpa: 1013.25, gas_resistance_ohm: Some(52_000) });
+        assert_eq!(frost, Reading { temperature: -50, humidity: 654, pressure: 101_325, gas: Some(52_000), iaq: None });
+        let data = Reading::from(&ClimateData { temperature_celsius: 21.46, humidity_percent: 101., pressure_hpa: -1., gas_resistance_ohm: None });
+        assert_eq!(data, Reading { temperature: 215, humidity: 1000, pressure: 0, gas: None, iaq: None });
+        assert_eq!(TemperatureUnit::Celsius.to_degrees(-55), -6);
+        // -5.2C is 22.64F
+        assert_eq!(TemperatureUnit::Fahrenheit.to_tenths(-52), 226);
+    }
+}
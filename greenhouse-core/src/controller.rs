@@ -0,0 +1,1107 @@
+use crate::air::AirQuality;
+use crate::arbiter::{Arbiter, Decision, Output, Outputs, Reason, OUTPUTS};
+use crate::buttons::{ButtonId, Buttons, Event};
+use crate::climate::{ClimateData, Reading, Tenths};
+use crate::fire::{FireState, Transition};
+use crate::hal::{Actuator, Button, ClimateSensor, Display, Monotonic, RealTimeClock, Servo, SmokeSensor, Storage};
+use crate::health::SensorHealth;
+use crate::menu::{Edit, EditState, Input, Screen, Session};
+use crate::pid::{pulse_duty, time_proportion, AutoTune, Pid, PidMode, TuneStep};
+use crate::relay::Relay;
+use crate::screens::{SCREENS, SERVICE_SCREENS};
+use crate::vent::{self, VentPosition};
+use crate::Preferences;
+use core::ops::Range;
+use embedded_hal::blocking::delay::DelayMs;
+
+const FIRE: &str = "Fire Present";
+const SENSOR_FAULT: &str = "Sensor fault";
+/// Time between two sensor polls
+pub const SENSOR_INTERVAL_MS: u32 = 1000;
+/// The air quality alert beeps once per period
+const AIR_ALERT_PERIOD_MS: u32 = 60_000;
+const AIR_ALERT_BEEP_MS: u32 = 1000;
+/// Hours in which the vent is held open while the climate sensor is faulty, as the sun
+/// could overheat the closed greenhouse
+const FAULT_VENT_HOURS: Range<u8> = 9..18;
+
+/// Everything the controller needs to interact with the outside world
+pub struct Hardware<B, M, A, C, L, D, E, R, T, V> {
+    pub up_button: B,
+    pub down_button: B,
+    pub select_button: B,
+    pub smoke_detector: M,
+    pub buzzer: A,
+    pub sprinklers: A,
+    pub roof_vent: A,
+    pub heater: A,
+    pub exhaust_fan: A,
+    pub sensor: C,
+    pub lcd: L,
+    pub delay: D,
+    pub storage: E,
+    /// None if the board has no real-time clock
+    pub rtc: Option<R>,
+    /// Milliseconds since boot; all scheduling is derived from it
+    pub timer: T,
+    /// None if the roof vent is only switched on and off
+    pub vent_servo: Option<V>,
+}
+
+/// The greenhouse controller
+/// The firmware and the simulator both call tick() in an endless loop
+pub struct Greenhouse<B, M, A, C, L, D, E, R, T, V> {
+    up_button: B,
+    down_button: B,
+    select_button: B,
+    smoke_detector: M,
+    buzzer: A,
+    sprinklers: Relay<A>,
+    roof_vent: Relay<A>,
+    heater: Relay<A>,
+    exhaust_fan: Relay<A>,
+    sensor: C,
+    lcd: L,
+    delay: D,
+    storage: E,
+    rtc: Option<R>,
+    timer: T,
+    vent_servo: Option<V>,
+    /// Opening of the vent servo the temperature asks for in proportional mode
+    vent_opening: u8,
+    pid: Pid,
+    /// The running auto-tune, replaces the PID controller until it is done
+    autotune: Option<AutoTune>,
+    air: AirQuality,
+    /// If the air quality is bad enough to beep
+    air_alert: bool,
+    health: SensorHealth,
+    /// If the sensor fault was acknowledged with a button
+    fault_acknowledged: bool,
+    /// What every rule wants each Output to do
+    rules: [Arbiter; OUTPUTS.len()],
+    /// The outcome of the last arbitration, for display
+    outputs: Outputs,
+    pub preferences: Preferences,
+    current_screen_index: u8,
+    /// If the hidden service screens are browsed instead of the regular ones
+    service: bool,
+    /// The Preferences being edited, None while the screens are browsed
+    editing: Option<EditState>,
+    session: Session,
+    fire: FireState,
+    buttons: Buttons,
+    last_poll: u32,
+    /// Time up to which the software clock has been advanced
+    last_second: u32,
+    /// The last good measurement, None until the sensor answered
+    reading: Option<Reading>,
+    last_sync_hour: u8,
+}
+
+impl<B, M, A, C, L, D, E, R, T, V> Greenhouse<B, M, A, C, L, D, E, R, T, V>
+where
+    B: Button,
+    M: SmokeSensor,
+    A: Actuator,
+    C: ClimateSensor,
+    L: Display,
+    D: DelayMs<u16>,
+    E: Storage,
+    R: RealTimeClock,
+    T: Monotonic,
+    V: Servo,
+{
+    /// Creates the controller
+    /// The Preferences are restored from storage and the clock from the RTC
+    /// If no RTC answers, the software clock is used instead
+    /// param hardware: The hardware to control
+    pub fn new(hardware: Hardware<B, M, A, C, L, D, E, R, T, V>) -> Self {
+        let mut preferences = Preferences::load(&hardware.storage);
+        let mut rtc = hardware.rtc;
+        match rtc.as_mut().map(|rtc| rtc.get_date()) {
+            Some(Ok(date)) => preferences.date = date,
+            _ => rtc = None,
+        }
+
+        let now = hardware.timer.millis();
+        Greenhouse {
+            up_button: hardware.up_button,
+            down_button: hardware.down_button,
+            select_button: hardware.select_button,
+            smoke_detector: hardware.smoke_detector,
+            buzzer: hardware.buzzer,
+            sprinklers: Relay::new(hardware.sprinklers),
+            roof_vent: Relay::new(hardware.roof_vent),
+            heater: Relay::new(hardware.heater),
+            exhaust_fan: Relay::new(hardware.exhaust_fan),
+            sensor: hardware.sensor,
+            lcd: hardware.lcd,
+            delay: hardware.delay,
+            storage: hardware.storage,
+            rtc,
+            timer: hardware.timer,
+            vent_servo: hardware.vent_servo,
+            vent_opening: 0,
+            pid: Pid::default(),
+            autotune: None,
+            air: AirQuality::default(),
+            air_alert: false,
+            health: SensorHealth::default(),
+            fault_acknowledged: false,
+            rules: Default::default(),
+            outputs: Outputs::default(),
+            last_sync_hour: preferences.date.hour(),
+            preferences,
+            current_screen_index: 0,
+            service: false,
+            editing: None,
+            session: Session::default(),
+            fire: FireState::Clear,
+            buttons: Buttons::default(),
+            last_poll: now,
+            last_second: now,
+            reading: None,
+        }
+    }
+
+    /// Runs a single iteration of the main app loop
+    pub fn tick(&mut self) {
+        self.delay.delay_ms(10);
+
+        // Make sure time is kept track of
+        self.keep_time();
+
+        // Smoke is checked every tick, no matter what else is going on
+        self.update_fire();
+
+        let now = self.timer.millis();
+        // The air quality alert and the sensor fault only chirp, and never drown out the fire alarm
+        if !self.fire.is_active() {
+            let alert = self.air_alert || (self.health.fault().is_some() && !self.fault_acknowledged);
+            self.buzzer.set_on(alert && now % AIR_ALERT_PERIOD_MS < AIR_ALERT_BEEP_MS);
+        }
+
+        let levels = [self.up_button.is_pressed(), self.down_button.is_pressed(), self.select_button.is_pressed()];
+        let events = self.buttons.update(levels, now);
+        let mut update_needed = !events.is_empty();
+        for event in events {
+            if self.fire.is_active() {
+                if let Event::Press(_) | Event::Chord = event {
+                    // Any button acknowledges the alarm
+                    self.fire.acknowledge();
+                    self.buzzer.set_on(false);
+                }
+            } else if self.shows_fault() {
+                if let Event::Press(_) | Event::Chord = event {
+                    // Any button dismisses the fault screen
+                    self.fault_acknowledged = true;
+                }
+            } else {
+                self.handle_button(event);
+            }
+        }
+
+        if should_poll(now, &mut self.last_poll) {
+            self.poll_sensors();
+            update_needed = true;
+        }
+
+        let outputs = self.outputs;
+        self.drive_outputs();
+        update_needed |= self.outputs != outputs;
+
+        if update_needed {
+            self.render();
+        }
+    }
+
+    /// Carries out the entry and exit actions of the fire state
+    /// The fire requests outrank every other rule while the fire state is active
+    fn update_fire(&mut self) {
+        let smoke = self.smoke_detector.is_smoke_detected();
+        let now = self.timer.millis();
+        match self.fire.update(smoke, now, self.roof_vent.is_on()) {
+            Some(Transition::Enter) => {
+                // Panic!!!
+                self.rules[Output::Sprinklers as usize].set(Reason::Fire, Some(true));
+                // Ensure windows are closed and nothing feeds the fire
+                self.rules[Output::RoofVent as usize].set(Reason::Fire, Some(false));
+                self.rules[Output::Heater as usize].set(Reason::Fire, Some(false));
+                self.rules[Output::ExhaustFan as usize].set(Reason::Fire, Some(false));
+                // Sound alarm
+                self.buzzer.set_on(true);
+                self.render();
+            }
+            Some(Transition::Exit { roof_open }) => {
+                // Safe; Disable sprinklers and open vent if it was open before
+                self.buzzer.set_on(false);
+                for rules in self.rules.iter_mut() {
+                    rules.set(Reason::Fire, None);
+                }
+                self.roof_vent.force(roof_open, now);
+                self.render();
+            }
+            None => {}
+        }
+    }
+
+    /// Renders the fire alarm, the editor, the sensor fault or the current screen
+    fn render(&mut self) {
+        if self.fire.is_active() {
+            self.lcd.set_cursor_blink(false);
+            render_screen(FIRE, true, &mut self.lcd);
+            render_screen(if self.fire.is_alarm_on() { "Press to silence" } else { "Alarm silenced" }, false, &mut self.lcd);
+        } else if let Some(state) = self.editing {
+            state.render(self.screen(), &self.preferences, &self.session, &mut self.lcd);
+        } else if let (true, Some(fault)) = (self.shows_fault(), self.health.fault()) {
+            self.lcd.set_cursor_blink(false);
+            render_screen(SENSOR_FAULT, true, &mut self.lcd);
+            render_screen(fault.label(), false, &mut self.lcd);
+        } else {
+            self.screen().render(self.reading.as_ref(), &self.outputs, &self.preferences, &self.session, &mut self.lcd);
+        }
+    }
+
+    /// returns if the sensor fault is shown instead of the current screen
+    /// It takes over until a button acknowledges it, but never interrupts editing
+    fn shows_fault(&self) -> bool {
+        self.health.fault().is_some() && !self.fault_acknowledged && self.editing.is_none()
+    }
+
+    /// returns the screens that are browsed
+    fn screens(&self) -> &'static [&'static dyn Screen] {
+        if self.service { &SERVICE_SCREENS } else { &SCREENS }
+    }
+
+    /// returns the screen that is currently selected
+    fn screen(&self) -> &'static dyn Screen {
+        self.screens()[self.current_screen_index as usize]
+    }
+
+    /// Switches screens or advances the editor
+    /// param event: The button event
+    fn handle_button(&mut self, event: Event) {
+        let Some(state) = self.editing else {
+            let count = self.screens().len() as u8;
+            match event {
+                Event::Press(id @ (ButtonId::Up | ButtonId::Down)) | Event::Repeat(id @ (ButtonId::Up | ButtonId::Down)) => {
+                    let next = id == ButtonId::Up;
+                    if !self.screen().on_browse(next, &self.preferences, &mut self.session) {
+                        self.current_screen_index = next_screen(self.current_screen_index, next, count);
+                    }
+                }
+                Event::Press(ButtonId::Select) => {
+                    self.lcd.clean_display();
+                    self.editing = EditState::enter(self.screen(), &mut self.preferences, &mut self.session);
+                }
+                Event::Chord => {
+                    // Enter or leave the hidden service menu
+                    self.service = !self.service;
+                    self.current_screen_index = 0;
+                }
+                _ => {}
+            }
+            return;
+        };
+
+        let (input, held) = match event {
+            Event::Press(ButtonId::Up) => (Input::Up, false),
+            Event::LongPress(ButtonId::Up) | Event::Repeat(ButtonId::Up) => (Input::Up, true),
+            Event::Press(ButtonId::Down) => (Input::Down, false),
+            Event::LongPress(ButtonId::Down) | Event::Repeat(ButtonId::Down) => (Input::Down, true),
+            Event::Press(ButtonId::Select) => (Input::Select, false),
+            Event::Chord => (Input::UpDown, false),
+            _ => return,
+        };
+        let screen = self.screen();
+        let committed = match state.handle(screen, input, held, &mut self.preferences, &mut self.session) {
+            Edit::Open(state) => {
+                self.editing = Some(state);
+                return;
+            }
+            Edit::Done { committed } => committed,
+        };
+
+        // Done editing
+        self.editing = None;
+        self.lcd.set_cursor_blink(false);
+        if !committed {
+            return;
+        }
+        if let (true, Some(rtc)) = (screen.sets_clock(), self.rtc.as_mut()) {
+            // The software clock stays correct if this fails
+            let _ = rtc.set_date(self.preferences.date);
+        }
+        self.preferences.save(&mut self.storage);
+    }
+
+    /// Measures the climate and updates the requests of the climate and schedule rules
+    fn poll_sensors(&mut self) {
+        // Correct the drift of the software clock once per hour
+        if self.preferences.date.hour() != self.last_sync_hour {
+            self.sync_clock();
+        }
+
+        let now = self.timer.millis();
+        match self.health.poll(&mut self.sensor, now) {
+            Some(data) => self.run_climate_rules(&data),
+            None if self.health.fault().is_some() => self.fall_back(),
+            // Keep acting on the last good reading until the sensor is considered faulty
+            None => {}
+        }
+
+        // Check if it is watering time
+        self.rules[Output::Sprinklers as usize].set(Reason::Schedule, self.preferences.is_watering_time().then_some(true));
+    }
+
+    /// Updates the requests of the climate rules from a good measurement
+    /// param data: The measurement
+    fn run_climate_rules(&mut self, data: &ClimateData) {
+        let mut reading = Reading::from(data);
+        self.fault_acknowledged = false;
+        for rules in self.rules.iter_mut() {
+            rules.set(Reason::Fault, None);
+        }
+
+        // Rules only request something when they need the actuator, so they can't undo each other
+        // Heat when too cold, open the vent when too hot and add the fan when that is not enough
+        let temperature = reading.temperature;
+        self.climate_rule(Output::Heater, Reason::Temperature, Preferences::needs_heating, temperature);
+        self.climate_rule(Output::RoofVent, Reason::Temperature, Preferences::needs_venting, temperature);
+        self.climate_rule(Output::ExhaustFan, Reason::Temperature, Preferences::needs_exhaust, temperature);
+        self.vent_opening = vent::opening(temperature, self.preferences.temperature.1, self.preferences.vent_band);
+
+        self.run_pid(temperature);
+
+        // Check if humidity is valid
+        self.climate_rule(Output::Sprinklers, Reason::Humidity, Preferences::needs_sprinkling, reading.humidity);
+
+        // Exchange the air when fumes or stale air build up
+        reading.iaq = self.air.update(reading.gas, reading.humidity, self.preferences.humidity_target());
+        self.climate_rule(Output::RoofVent, Reason::AirQuality, Preferences::needs_air_exchange, reading.iaq);
+        self.climate_rule(Output::ExhaustFan, Reason::AirQuality, Preferences::needs_air_exchange, reading.iaq);
+        self.air_alert = self.preferences.needs_air_alert(reading.iaq, self.air_alert);
+        self.reading = Some(reading);
+    }
+
+    /// Replaces the climate rules with a safe state while the sensor is faulty
+    /// Nothing is heated blindly and the vent is only open in the daytime; the schedule,
+    /// manual overrides and fire suppression keep working as usual
+    fn fall_back(&mut self) {
+        for rules in self.rules.iter_mut() {
+            for reason in [Reason::Temperature, Reason::Humidity, Reason::AirQuality] {
+                rules.set(reason, None);
+            }
+        }
+        let daytime = FAULT_VENT_HOURS.contains(&self.preferences.date.hour());
+        self.rules[Output::RoofVent as usize].set(Reason::Fault, Some(daytime));
+        self.rules[Output::Heater as usize].set(Reason::Fault, Some(false));
+        self.rules[Output::ExhaustFan as usize].set(Reason::Fault, Some(false));
+
+        self.pid.reset();
+        self.autotune = None;
+        self.session.autotune = false;
+        self.outputs.pid = None;
+        self.outputs.tuning = None;
+        if let Some(reading) = self.reading.as_mut() {
+            reading.iaq = None;
+        }
+        self.air_alert = false;
+    }
+
+    /// Lets the PID controller, or its auto-tune, drive the heater or vent instead of the on/off rule
+    /// param measurement: Temperature in tenths of a degree Celsius
+    fn run_pid(&mut self, measurement: Tenths) {
+        let output = match self.preferences.pid_mode {
+            PidMode::Off => {
+                self.pid.reset();
+                self.autotune = None;
+                self.session.autotune = false;
+                self.outputs.pid = None;
+                self.outputs.tuning = None;
+                return;
+            }
+            PidMode::Heater => Output::Heater,
+            PidMode::Vent => Output::RoofVent,
+        };
+        let now = self.timer.millis();
+        let cooling = self.preferences.pid_mode.is_cooling();
+        // The heater holds the lower threshold, the vent the upper one
+        let setpoint = if cooling { self.preferences.temperature.1 } else { self.preferences.temperature.0 };
+        let limits = self.preferences.pid_limits;
+
+        let duty = if self.session.autotune {
+            let tune = self.autotune.get_or_insert_with(|| AutoTune::new(setpoint, cooling, limits, now));
+            match tune.update(measurement, now) {
+                TuneStep::Running(duty) => duty,
+                step => {
+                    if let TuneStep::Done(gains) = step {
+                        self.preferences.pid_gains = gains;
+                        self.preferences.save(&mut self.storage);
+                    }
+                    self.session.autotune = false;
+                    self.autotune = None;
+                    self.pid.reset();
+                    limits.0
+                }
+            }
+        } else {
+            self.autotune = None;
+            self.pid.update(setpoint, measurement, cooling, now, self.preferences.pid_gains, limits)
+        };
+        self.outputs.pid = Some(duty);
+        self.outputs.tuning = self.autotune.as_ref().map(AutoTune::cycles);
+
+        // A vent servo is positioned directly, everything else is on for part of the window,
+        // in pulses that respect its minimum run and rest times
+        let on = if output == Output::RoofVent && self.vent_servo.is_some() {
+            duty > 0
+        } else {
+            time_proportion(pulse_duty(duty, self.preferences.timing(output)), now)
+        };
+        self.rules[output as usize].set(Reason::Temperature, on.then_some(true));
+    }
+
+    /// Updates the request of a rule that switches an actuator on when a measurement is off
+    /// param output: The actuator
+    /// param reason: The rule
+    /// param check: Decides from the measurement and if the rule is active
+    /// param value: The measurement
+    fn climate_rule<Q>(&mut self, output: Output, reason: Reason, check: fn(&Preferences, Q, bool) -> bool, value: Q) {
+        let rules = &mut self.rules[output as usize];
+        let active = rules.get(reason).is_some();
+        rules.set(reason, check(&self.preferences, value, active).then_some(true));
+    }
+
+    /// Resolves the requests of every rule and switches the actuators
+    /// Fire suppression and manual overrides switch right away, everything else
+    /// waits for the minimum run and rest times
+    fn drive_outputs(&mut self) {
+        let now = self.timer.millis();
+        for output in OUTPUTS {
+            let rules = &mut self.rules[output as usize];
+            rules.set(Reason::Manual, self.session.overrides[output as usize]);
+            let decision = rules.resolve();
+            let timing = self.preferences.timing(output);
+            let relay = self.relay(output);
+            drive(relay, decision, now, timing);
+            // Show the actual state, which lags behind while a minimum time runs
+            let on = relay.is_on();
+            self.outputs.set(output, Decision { on, ..decision });
+        }
+        self.position_vent();
+    }
+
+    /// Moves the vent servo along with the roof vent output
+    /// With PID or in proportional mode the temperature rule opens it only as far as needed,
+    /// everything else opens it fully. While the travel limits are calibrated the
+    /// servo holds the limit being edited instead.
+    fn position_vent(&mut self) {
+        let calibration = self.editing.and_then(|state| self.screen().calibration_position(state.field, &self.preferences));
+        let Some(servo) = self.vent_servo.as_mut() else {
+            return;
+        };
+
+        let opening = match (self.roof_vent.is_on(), self.outputs.get(Output::RoofVent).reason) {
+            (false, _) => 0,
+            (true, Some(Reason::Temperature)) if self.preferences.pid_mode == PidMode::Vent => self.outputs.pid.unwrap_or(100),
+            (true, Some(Reason::Temperature)) if self.preferences.proportional_vent => self.vent_opening,
+            (true, _) => 100,
+        };
+        let travel = self.preferences.vent_travel;
+        servo.set_position(calibration.unwrap_or(vent::to_drive(opening, travel)));
+        let feedback = servo.feedback().and_then(|position| vent::from_drive(position, travel));
+        self.outputs.vent = Some(VentPosition { opening, feedback });
+    }
+
+    /// param output: The actuator
+    /// returns the actuator driven by the rules for an Output
+    fn relay(&mut self, output: Output) -> &mut Relay<A> {
+        match output {
+            Output::RoofVent => &mut self.roof_vent,
+            Output::Sprinklers => &mut self.sprinklers,
+            Output::Heater => &mut self.heater,
+            Output::ExhaustFan => &mut self.exhaust_fan,
+        }
+    }
+
+    /// Advances the software clock by every full second that passed since the last call
+    fn keep_time(&mut self) {
+        let seconds = self.timer.millis().wrapping_sub(self.last_second) / 1000;
+        if seconds > 0 {
+            self.last_second = self.last_second.wrapping_add(seconds * 1000);
+            self.preferences.date = self.preferences.date.add_seconds(seconds);
+        }
+    }
+
+    /// Sets the software clock to the time of the RTC
+    /// The software clock keeps running on its own if the RTC can't be read
+    fn sync_clock(&mut self) {
+        if let Some(Ok(date)) = self.rtc.as_mut().map(|rtc| rtc.get_date()) {
+            self.preferences.date = date;
+        }
+        self.last_sync_hour = self.preferences.date.hour();
+    }
+}
+
+/// Switches an actuator to the outcome of its arbitration
+/// param relay: The actuator
+/// param decision: The outcome of the arbitration
+/// param now: Milliseconds since boot
+/// param timing: Minimum run and rest time in seconds
+fn drive<A: Actuator>(relay: &mut Relay<A>, decision: Decision, now: u32, timing: (u16, u16)) {
+    match decision.reason {
+        Some(Reason::Fire | Reason::Manual) => relay.force(decision.on, now),
+        _ => {
+            relay.request(decision.on);
+            relay.apply(now, timing);
+        }
+    }
+}
+
+/// Checks if the sensors are due to be polled
+/// param now: Milliseconds since boot
+/// param last_poll: When the sensors were last polled
+/// returns if the sensors should be polled
+pub fn should_poll(now: u32, last_poll: &mut u32) -> bool {
+    if now.wrapping_sub(*last_poll) >= SENSOR_INTERVAL_MS {
+        *last_poll = now;
+        return true;
+    }
+    false
+}
+
+/// Basic function for rendering text onto the LCD
+/// It only clears the screen when the top line is written to
+/// param line: text to render
+/// param top_line: if the top line is to be written to
+/// param lcd: LCD instance
+pub fn render_screen<L: Display + ?Sized>(line: &str, top_line: bool, lcd: &mut L) {
+    // Set cursor to the correct line
+    if top_line {
+        // Reset screen
+        lcd.clean_display();
+        lcd.set_cursor_pos((0, 0));
+    } else {
+        lcd.set_cursor_pos((0, 1));
+    }
+    lcd.write_str_to_cur(line);
+}
+
+/// Iterates forwards or backwards through Screens
+/// param current_screen: The current screen being displayed
+/// param next: Whether to iterate forward; If false, iterate backwards
+/// param count: The amount of screens
+/// returns: The next Screen
+pub fn next_screen(mut current_screen_index: u8, next: bool, count: u8) -> u8 {
+    if next {
+        current_screen_index = (current_screen_index + 1) % count;
+    } else {
+        current_screen_index = (current_screen_index + count - 1) % count;
+    }
+    current_screen_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::air::BURN_IN_SAMPLES;
+    use crate::fire::MIN_SPRINKLER_MS;
+    use crate::health::{SensorFault, MAX_FAILED_POLLS};
+    use crate::pid::PWM_WINDOW_MS;
+    use crate::datetime::DateTime;
+    use crate::schedule::Watering;
+    use crate::screens::TemperatureScreen;
+
+    struct MockButton(bool);
+
+    impl Button for MockButton {
+        fn is_pressed(&self) -> bool {
+            self.0
+        }
+    }
+
+    #[derive(Default)]
+    struct MockActuator(bool);
+
+    impl Actuator for MockActuator {
+        fn set_on(&mut self, on: bool) {
+            self.0 = on;
+        }
+
+        fn is_on(&self) -> bool {
+            self.0
+        }
+    }
+
+    struct MockSensor(Option<ClimateData>);
+
+    impl ClimateSensor for MockSensor {
+        type Error = ();
+
+        fn prepare(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn measure(&mut self) -> Result<ClimateData, ()> {
+            self.0.ok_or(())
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayMs<u16> for NoDelay {
+        fn delay_ms(&mut self, _ms: u16) {}
+    }
+
+    struct MockSmoke(bool);
+
+    impl SmokeSensor for MockSmoke {
+        fn is_smoke_detected(&self) -> bool {
+            self.0
+        }
+    }
+
+    struct MockLcd;
+
+    impl Display for MockLcd {
+        fn clean_display(&mut self) {}
+        fn set_cursor_pos(&mut self, _pos: (u8, u8)) {}
+        fn write_str_to_cur(&mut self, _line: &str) {}
+        fn set_cursor_blink(&mut self, _on: bool) {}
+    }
+
+    struct MockStorage([u8; 128]);
+
+    impl Storage for MockStorage {
+        fn read_byte(&self, address: u16) -> u8 {
+            self.0[address as usize]
+        }
+
+        fn write_byte(&mut self, address: u16, value: u8) {
+            self.0[address as usize] = value;
+        }
+    }
+
+    struct NoRtc;
+
+    impl RealTimeClock for NoRtc {
+        type Error = ();
+
+        fn get_date(&mut self) -> Result<DateTime, ()> {
+            Err(())
+        }
+
+        fn set_date(&mut self, _date: DateTime) -> Result<(), ()> {
+            Err(())
+        }
+    }
+
+    struct MockTimer(u32);
+
+    impl Monotonic for MockTimer {
+        fn millis(&self) -> u32 {
+            self.0
+        }
+    }
+
+    /// A servo with perfect position feedback
+    #[derive(Default)]
+    struct MockServo(u8);
+
+    impl Servo for MockServo {
+        fn set_position(&mut self, position: u8) {
+            self.0 = position;
+        }
+
+        fn feedback(&mut self) -> Option<u8> {
+            Some(self.0)
+        }
+    }
+
+    const CALM: ClimateData = ClimateData { temperature_celsius: 20., humidity_percent: 65., pressure_hpa: 1013., gas_resistance_ohm: None };
+    /// Hot enough to open the vent and run the exhaust fan
+    const HOT: ClimateData = ClimateData { temperature_celsius: 35., ..CALM };
+
+    type TestGreenhouse = Greenhouse<MockButton, MockSmoke, MockActuator, MockSensor, MockLcd, NoDelay, MockStorage, NoRtc, MockTimer, MockServo>;
+
+    /// Creates a controller measuring the given climate
+    fn greenhouse(reading: ClimateData) -> TestGreenhouse {
+        Greenhouse::new(Hardware {
+            up_button: MockButton(false),
+            down_button: MockButton(false),
+            select_button: MockButton(false),
+            smoke_detector: MockSmoke(false),
+            buzzer: MockActuator::default(),
+            sprinklers: MockActuator::default(),
+            roof_vent: MockActuator::default(),
+            heater: MockActuator::default(),
+            exhaust_fan: MockActuator::default(),
+            sensor: MockSensor(Some(reading)),
+            lcd: MockLcd,
+            delay: NoDelay,
+            storage: MockStorage([0xFF; 128]),
+            rtc: None,
+            timer: MockTimer(0),
+            vent_servo: Some(MockServo::default()),
+        })
+    }
+
+    #[test]
+    fn sensors_are_polled_every_second() {
+        let mut last_poll = 0;
+        assert!(!should_poll(999, &mut last_poll));
+        assert!(should_poll(1003, &mut last_poll));
+        assert_eq!(last_poll, 1003);
+        assert!(!should_poll(2000, &mut last_poll));
+    }
+
+    #[test]
+    fn sensor_polling_survives_timer_overflow() {
+        let mut last_poll = u32::MAX - 500;
+        assert!(!should_poll(400, &mut last_poll));
+        assert!(should_poll(500, &mut last_poll));
+    }
+
+    #[test]
+    fn screens_wrap_around() {
+        assert_eq!(next_screen(4, true, 5), 0);
+        assert_eq!(next_screen(0, false, 5), 4);
+    }
+
+    /// Runs the controller for a while, 10ms per tick
+    fn run(greenhouse: &mut TestGreenhouse, millis: u32) {
+        for _ in 0..millis / 10 {
+            greenhouse.timer.0 += 10;
+            greenhouse.tick();
+        }
+    }
+
+    /// Presses and releases a button
+    fn press(greenhouse: &mut TestGreenhouse, button: ButtonId) {
+        match button {
+            ButtonId::Up => greenhouse.up_button.0 = true,
+            ButtonId::Down => greenhouse.down_button.0 = true,
+            ButtonId::Select => greenhouse.select_button.0 = true,
+        }
+        run(greenhouse, 200);
+        greenhouse.up_button.0 = false;
+        greenhouse.down_button.0 = false;
+        greenhouse.select_button.0 = false;
+        run(greenhouse, 100);
+    }
+
+    /// Finds a screen by what it edits, so the tests don't depend on the order of the screens
+    /// param screens: The screens to search
+    /// param edited: Checks the Preferences after UP was pressed on the first field of a screen
+    /// returns the index of the screen
+    fn screen_editing(screens: &[&dyn Screen], edited: fn(&Preferences) -> bool) -> u8 {
+        let position = screens.iter().position(|screen| {
+            let (mut preferences, mut session) = (Preferences::default(), Session::default());
+            if let Some(state) = EditState::enter(*screen, &mut preferences, &mut session) {
+                state.handle(*screen, Input::Up, false, &mut preferences, &mut session);
+            }
+            edited(&preferences)
+        });
+        position.unwrap() as u8
+    }
+
+    #[test]
+    fn climate_control_keeps_running_while_editing() {
+        let mut greenhouse = greenhouse(HOT);
+
+        press(&mut greenhouse, ButtonId::Select);
+        assert_eq!(greenhouse.editing, EditState::enter(&TemperatureScreen, &mut Preferences::default(), &mut Session::default()));
+
+        greenhouse.timer.0 += SENSOR_INTERVAL_MS;
+        greenhouse.tick();
+        assert!(greenhouse.roof_vent.is_on());
+        assert!(greenhouse.editing.is_some());
+    }
+
+    #[test]
+    fn finished_edits_are_saved() {
+        let mut greenhouse = greenhouse(CALM);
+        greenhouse.current_screen_index = screen_editing(&SCREENS, |preferences| preferences.humidity != Preferences::default().humidity);
+        for button in [ButtonId::Select, ButtonId::Up, ButtonId::Select, ButtonId::Select] {
+            press(&mut greenhouse, button);
+        }
+        assert_eq!(greenhouse.editing, None);
+        assert_eq!(Preferences::load(&greenhouse.storage).humidity, (61, 70));
+    }
+
+    #[test]
+    fn a_cancelled_date_is_not_stored() {
+        let mut greenhouse = greenhouse(CALM);
+        greenhouse.current_screen_index = SCREENS.iter().position(|screen| screen.sets_clock()).unwrap() as u8;
+        press(&mut greenhouse, ButtonId::Select);
+        press(&mut greenhouse, ButtonId::Up);
+        greenhouse.handle_button(Event::Chord);
+        assert_eq!(greenhouse.editing, None);
+        assert_eq!(greenhouse.storage.0, [0xFF; 128]);
+
+        // Open the editor, pass the five fields and confirm without a change
+        for _ in 0..7 {
+            press(&mut greenhouse, ButtonId::Select);
+        }
+        assert_eq!(greenhouse.editing, None);
+        assert_eq!(greenhouse.storage.0, [0xFF; 128]);
+
+        // Change the year, pass the remaining fields and confirm
+        press(&mut greenhouse, ButtonId::Select);
+        press(&mut greenhouse, ButtonId::Up);
+        for _ in 0..6 {
+            press(&mut greenhouse, ButtonId::Select);
+        }
+        assert_eq!(greenhouse.editing, None);
+        assert_ne!(greenhouse.storage.0, [0xFF; 128]);
+    }
+
+    #[test]
+    fn smoke_is_handled_immediately_and_latched() {
+        let mut greenhouse = greenhouse(HOT);
+        greenhouse.timer.0 = SENSOR_INTERVAL_MS;
+        greenhouse.tick();
+        assert!(greenhouse.roof_vent.is_on());
+
+        // Detected even while editing and without waiting for a sensor poll
+        press(&mut greenhouse, ButtonId::Select);
+        greenhouse.smoke_detector.0 = true;
+        greenhouse.tick();
+        assert!(greenhouse.sprinklers.is_on() && greenhouse.buzzer.is_on());
+        assert!(!greenhouse.roof_vent.is_on());
+
+        // Smoke clearing doesn't stop the sprinklers
+        greenhouse.smoke_detector.0 = false;
+        greenhouse.timer.0 += MIN_SPRINKLER_MS;
+        greenhouse.tick();
+        assert!(greenhouse.sprinklers.is_on() && greenhouse.buzzer.is_on());
+
+        // Acknowledging silences the alarm and ends the fire state
+        press(&mut greenhouse, ButtonId::Up);
+        greenhouse.tick();
+        assert!(!greenhouse.sprinklers.is_on() && !greenhouse.buzzer.is_on());
+        assert!(greenhouse.roof_vent.is_on());
+        assert_eq!(greenhouse.editing, EditState::enter(&TemperatureScreen, &mut Preferences::default(), &mut Session::default()));
+    }
+
+    #[test]
+    fn vent_is_not_cycled_faster_than_its_minimum_times() {
+        let mut greenhouse = greenhouse(HOT);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert!(greenhouse.roof_vent.is_on());
+
+        // 26.2C is 79F, inside the range but not by the deadband yet
+        greenhouse.sensor.0 = Some(ClimateData { temperature_celsius: 26.2, ..HOT });
+        run(&mut greenhouse, 120_000);
+        assert!(greenhouse.roof_vent.is_on());
+
+        greenhouse.sensor.0 = Some(ClimateData { temperature_celsius: 22., ..HOT });
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert!(!greenhouse.roof_vent.is_on());
+        // Hot again right away, but the vent has to rest first
+        greenhouse.sensor.0 = Some(HOT);
+        run(&mut greenhouse, 30_000);
+        assert!(!greenhouse.roof_vent.is_on());
+        run(&mut greenhouse, 31_000);
+        assert!(greenhouse.roof_vent.is_on());
+    }
+
+    #[test]
+    fn cold_heats_and_heat_is_vented_in_stages() {
+        let cold = ClimateData { temperature_celsius: 5., humidity_percent: 65., pressure_hpa: 1013., gas_resistance_ohm: None };
+        let mut greenhouse = greenhouse(cold);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert!(greenhouse.heater.is_on());
+        assert!(!greenhouse.roof_vent.is_on() && !greenhouse.exhaust_fan.is_on());
+
+        // 28C is 82F, above the vent but below the fan threshold
+        greenhouse.sensor.0 = Some(ClimateData { temperature_celsius: 28., ..cold });
+        run(&mut greenhouse, 120_000);
+        assert!(!greenhouse.heater.is_on() && greenhouse.roof_vent.is_on());
+        assert!(!greenhouse.exhaust_fan.is_on());
+
+        greenhouse.sensor.0 = Some(ClimateData { temperature_celsius: 35., ..cold });
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert!(greenhouse.roof_vent.is_on() && greenhouse.exhaust_fan.is_on());
+    }
+
+    #[test]
+    fn proportional_vent_opens_as_far_as_needed() {
+        // 28.4C is 83F, about halfway through the proportional band
+        let warm = ClimateData { temperature_celsius: 28.4, humidity_percent: 65., pressure_hpa: 1013., gas_resistance_ohm: None };
+        let mut greenhouse = greenhouse(warm);
+        greenhouse.preferences.vent_travel = (20, 80);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert_eq!(greenhouse.vent_servo.as_ref().unwrap().0, 80);
+
+        greenhouse.preferences.proportional_vent = true;
+        greenhouse.tick();
+        assert_eq!(greenhouse.vent_servo.as_ref().unwrap().0, 50);
+        assert_eq!(greenhouse.outputs.vent, Some(VentPosition { opening: 51, feedback: Some(50) }));
+    }
+
+    #[test]
+    fn vent_servo_follows_the_travel_limit_being_calibrated() {
+        let mut greenhouse = greenhouse(CALM);
+        greenhouse.service = true;
+        greenhouse.current_screen_index = screen_editing(&SERVICE_SCREENS, |preferences| preferences.vent_travel != Preferences::default().vent_travel);
+        press(&mut greenhouse, ButtonId::Select);
+        press(&mut greenhouse, ButtonId::Up);
+        assert_eq!(greenhouse.vent_servo.as_ref().unwrap().0, 1);
+        press(&mut greenhouse, ButtonId::Select);
+        assert_eq!(greenhouse.vent_servo.as_ref().unwrap().0, 100);
+        press(&mut greenhouse, ButtonId::Select);
+        assert_eq!(greenhouse.vent_servo.as_ref().unwrap().0, 1);
+    }
+
+    #[test]
+    fn pid_drives_the_heater_by_time_proportioning() {
+        // 0.6C below the setpoint of 15.6C
+        let cool = ClimateData { temperature_celsius: 15., humidity_percent: 65., pressure_hpa: 1013., gas_resistance_ohm: None };
+        let mut greenhouse = greenhouse(cool);
+        greenhouse.preferences.pid_mode = PidMode::Heater;
+        greenhouse.preferences.pid_gains = (2500, 0, 0);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert_eq!(greenhouse.outputs.pid, Some(15));
+        assert!(greenhouse.heater.is_on());
+
+        // Off for the rest of the window once 15% of it have passed
+        run(&mut greenhouse, PWM_WINDOW_MS / 4);
+        assert!(!greenhouse.heater.is_on());
+    }
+
+    #[test]
+    fn small_pid_outputs_are_not_stretched_by_the_minimum_times() {
+        // 0.6C below the setpoint gives 6%, too short for the 2 minute run time of the heater
+        let cool = ClimateData { temperature_celsius: 15., humidity_percent: 65., pressure_hpa: 1013., gas_resistance_ohm: None };
+        let mut greenhouse = greenhouse(cool);
+        greenhouse.preferences.pid_mode = PidMode::Heater;
+        greenhouse.preferences.pid_gains = (1000, 0, 0);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert_eq!(greenhouse.outputs.pid, Some(6));
+        assert!(!greenhouse.heater.is_on());
+
+        // 12% runs for the minimum time instead
+        greenhouse.preferences.pid_gains = (2000, 0, 0);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert_eq!(greenhouse.outputs.pid, Some(12));
+        assert!(greenhouse.heater.is_on());
+        run(&mut greenhouse, PWM_WINDOW_MS / 5);
+        assert!(!greenhouse.heater.is_on());
+    }
+
+    #[test]
+    fn pid_positions_the_vent_servo() {
+        // 3.3C above the setpoint of 26.7C
+        let hot = ClimateData { temperature_celsius: 30., ..CALM };
+        let mut greenhouse = greenhouse(hot);
+        greenhouse.preferences.pid_mode = PidMode::Vent;
+        greenhouse.preferences.pid_gains = (1000, 0, 0);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert!(greenhouse.roof_vent.is_on());
+        assert_eq!(greenhouse.vent_servo.as_ref().unwrap().0, 33);
+    }
+
+    #[test]
+    fn fumes_are_vented_and_beeped_at_once_the_baseline_is_learned() {
+        let clean = ClimateData { temperature_celsius: 20., humidity_percent: 65., pressure_hpa: 1013., gas_resistance_ohm: Some(50_000) };
+        let mut greenhouse = greenhouse(clean);
+        greenhouse.preferences.iaq_vent = 150;
+        greenhouse.preferences.iaq_alert = 250;
+        run(&mut greenhouse, BURN_IN_SAMPLES as u32 * SENSOR_INTERVAL_MS);
+        assert_eq!(greenhouse.reading.and_then(|reading| reading.iaq), None);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert_eq!(greenhouse.reading.and_then(|reading| reading.iaq), Some(0));
+
+        // A fifth of the clean air resistance
+        greenhouse.sensor.0 = Some(ClimateData { gas_resistance_ohm: Some(10_000), ..clean });
+        greenhouse.timer.0 = 6 * AIR_ALERT_PERIOD_MS - SENSOR_INTERVAL_MS;
+        run(&mut greenhouse, SENSOR_INTERVAL_MS + 10);
+        assert_eq!(greenhouse.reading.and_then(|reading| reading.iaq), Some(300));
+        assert!(greenhouse.roof_vent.is_on() && greenhouse.exhaust_fan.is_on());
+        assert_eq!(greenhouse.outputs.get(Output::ExhaustFan).reason, Some(Reason::AirQuality));
+        assert!(greenhouse.buzzer.is_on());
+        run(&mut greenhouse, AIR_ALERT_BEEP_MS);
+        assert!(!greenhouse.buzzer.is_on());
+    }
+
+    #[test]
+    fn a_faulty_sensor_holds_the_safe_state_and_fire_suppression_keeps_working() {
+        let cold = ClimateData { temperature_celsius: 5., ..CALM };
+        let mut greenhouse = greenhouse(cold);
+        greenhouse.preferences.date = DateTime::new(2024, 6, 1, 12, 0, 0).unwrap();
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert!(greenhouse.heater.is_on());
+
+        // The last good reading is kept for a few polls
+        greenhouse.sensor.0 = None;
+        run(&mut greenhouse, (MAX_FAILED_POLLS as u32 - 1) * SENSOR_INTERVAL_MS);
+        assert_eq!(greenhouse.health.fault(), None);
+        assert!(greenhouse.heater.is_on());
+
+        run(&mut greenhouse, 120_000);
+        assert_eq!(greenhouse.health.fault(), Some(SensorFault::NoResponse));
+        assert_eq!(greenhouse.outputs.get(Output::Heater), Decision { on: false, reason: Some(Reason::Fault) });
+        assert!(!greenhouse.heater.is_on() && greenhouse.roof_vent.is_on());
+        assert!(greenhouse.shows_fault());
+        press(&mut greenhouse, ButtonId::Up);
+        assert!(!greenhouse.shows_fault());
+        assert_eq!(greenhouse.current_screen_index, 0);
+
+        greenhouse.smoke_detector.0 = true;
+        greenhouse.tick();
+        assert!(greenhouse.sprinklers.is_on() && !greenhouse.roof_vent.is_on());
+        greenhouse.smoke_detector.0 = false;
+
+        // A good reading ends the fault
+        greenhouse.sensor.0 = Some(cold);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert_eq!(greenhouse.health.fault(), None);
+        assert_eq!(greenhouse.outputs.get(Output::Heater).reason, Some(Reason::Fire));
+    }
+
+    #[test]
+    fn humidity_misting_runs_outside_the_watering_window() {
+        let dry = ClimateData { temperature_celsius: 21., humidity_percent: 40., pressure_hpa: 1013., gas_resistance_ohm: None };
+        let mut greenhouse = greenhouse(dry);
+        run(&mut greenhouse, SENSOR_INTERVAL_MS);
+        assert!(greenhouse.sprinklers.is_on());
+        assert_eq!(greenhouse.outputs.get(Output::Sprinklers), Decision { on: true, reason: Some(Reason::Humidity) });
+
+        // A manual override beats the humidity rule right away
+        greenhouse.session.overrides[Output::Sprinklers as usize] = Some(false);
+        greenhouse.tick();
+        assert!(!greenhouse.sprinklers.is_on());
+        assert_eq!(greenhouse.outputs.get(Output::Sprinklers).reason, Some(Reason::Manual));
+    }
+
+    #[test]
+    fn up_pages_through_the_watering_schedules_first() {
+        let mut greenhouse = greenhouse(CALM);
+        greenhouse.preferences.watering.push(Watering::default()).unwrap();
+        greenhouse.preferences.watering.push(Watering::default()).unwrap();
+        let pages = |screen: &&dyn Screen| screen.on_browse(true, &greenhouse.preferences, &mut Session::default());
+        let watering = SCREENS.iter().position(pages).unwrap() as u8;
+        greenhouse.current_screen_index = watering;
+        for (slot, screen) in [(1, watering), (1, watering + 1)] {
+            greenhouse.handle_button(Event::Press(ButtonId::Up));
+            assert_eq!((greenhouse.session.watering_slot, greenhouse.current_screen_index), (slot, screen));
+        }
+    }
+
+    #[test]
+    fn chord_opens_the_hidden_service_menu() {
+        let mut greenhouse = greenhouse(CALM);
+        greenhouse.current_screen_index = SCREENS.len() as u8 - 1;
+        greenhouse.up_button.0 = true;
+        greenhouse.down_button.0 = true;
+        run(&mut greenhouse, 2000);
+        greenhouse.up_button.0 = false;
+        greenhouse.down_button.0 = false;
+        run(&mut greenhouse, 100);
+        assert!(greenhouse.service);
+        assert_eq!(greenhouse.current_screen_index, 0);
+    }
+}
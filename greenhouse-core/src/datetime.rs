@@ -0,0 +1,329 @@
+//! Calendar date and time of the clocks
+//!
+//! Day and month count from 1, as on the LCD and in the RTC. A DateTime can only be
+//! created through its checked constructor and changed by whole durations, so it always
+//! holds a valid date between FIRST_YEAR and LAST_YEAR (the two digit years of the RTC).
+//! The fields are ordered from the year down, so comparing DateTimes compares the times.
+
+use core::ops::{Add, Sub};
+use core::time::Duration;
+use heapless::String;
+use ufmt::uwrite;
+
+/// Years the clock can hold
+pub const FIRST_YEAR: u16 = 2000;
+pub const LAST_YEAR: u16 = 2099;
+const DAY_SECONDS: u32 = 24 * 60 * 60;
+/// Names of the weekdays, starting with Monday
+pub const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Writes a part of a date or time with at least two digits, e.g. "07"
+/// param out: The string to append to
+/// param value: The hour, minute, day, etc.
+pub fn write_two_digits<const N: usize>(out: &mut String<N>, value: u8) {
+    // ufmt has no zero padding
+    let padding = if value < 10 { "0" } else { "" };
+    uwrite!(out, "{}{}", padding, value).unwrap();
+}
+
+/// Calculates if it is leap year
+/// param year: The year
+pub fn is_leap_year(year: u16) -> bool {
+    // Every fourth year, but only every fourth century
+    match (year % 4, year % 100, year % 400) {
+        (_, _, 0) => true,
+        (_, 0, _) => false,
+        (0, _, _) => true,
+        _ => false,
+    }
+}
+
+/// Gets the amount of days in a month
+/// param month: The month (1 - 12)
+/// param year: The year the month is in
+/// returns the amount of days in the month
+pub fn days_in_month(month: u8, year: u16) -> u8 {
+    match month {
+        2 => if is_leap_year(year) { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Gets the day of the week (Sakamoto's method)
+/// param day: The day of the month (1 - 31)
+/// param month: The month (1 - 12)
+/// param year: The year
+/// returns the day of the week, 0 is Monday
+pub fn weekday(day: u8, month: u8, year: u16) -> u8 {
+    const OFFSETS: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let year = if month < 3 { year.saturating_sub(1) } else { year };
+    let sunday_based = (year + year / 4 - year / 100 + year / 400 + OFFSETS[(month as usize + 11) % 12] + day as u16) % 7;
+    ((sunday_based + 6) % 7) as u8
+}
+
+/// param year: The year
+/// returns the amount of days in the year
+fn days_in_year(year: u16) -> u32 {
+    if is_leap_year(year) { 366 } else { 365 }
+}
+
+/// A valid date and time of day
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct DateTime {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+/// The clock stops here
+const LAST: DateTime = DateTime { year: LAST_YEAR, month: 12, day: 31, hour: 23, minute: 59, second: 59 };
+
+impl Default for DateTime {
+    /// 00:00:00 Jan 1 2000
+    fn default() -> Self {
+        DateTime { year: FIRST_YEAR, month: 1, day: 1, hour: 0, minute: 0, second: 0 }
+    }
+}
+
+impl DateTime {
+    /// Creates a DateTime
+    /// param year: FIRST_YEAR - LAST_YEAR
+    /// param month: 1 - 12
+    /// param day: 1 - the days of the month
+    /// param hour: 0 - 23
+    /// param minute: 0 - 59
+    /// param second: 0 - 59
+    /// returns None if any of the values is out of range
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<Self> {
+        let valid = (FIRST_YEAR..=LAST_YEAR).contains(&year)
+            && (1..=12).contains(&month)
+            && (1..=days_in_month(month, year)).contains(&day)
+            && hour < 24
+            && minute < 60
+            && second < 60;
+        valid.then_some(DateTime { year, month, day, hour, minute, second })
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// returns the month, 1 is January
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// returns the day of the month, starting at 1
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// returns the amount of days in the month of the date
+    pub fn days_in_month(&self) -> u8 {
+        days_in_month(self.month, self.year)
+    }
+
+    /// returns the day of the week, 0 is Monday
+    pub fn weekday(&self) -> u8 {
+        weekday(self.day, self.month, self.year)
+    }
+
+    /// returns the minutes since midnight
+    pub fn minute_of_day(&self) -> u16 {
+        self.hour as u16 * 60 + self.minute as u16
+    }
+
+    /// Advances the clock by carrying from the seconds up, for the software clock ticking every second
+    /// Much cheaper than adding a Duration on the AVR, jumps of a day or more still take the full conversion
+    /// param seconds: Seconds to add
+    /// returns the DateTime, which stops at the end of LAST_YEAR
+    pub fn add_seconds(self, seconds: u32) -> Self {
+        if seconds >= DAY_SECONDS {
+            return self + Duration::from_secs(seconds as u64);
+        }
+        let mut date = self;
+        let seconds = date.second as u32 + seconds;
+        date.second = (seconds % 60) as u8;
+        let minutes = date.minute as u32 + seconds / 60;
+        date.minute = (minutes % 60) as u8;
+        let hours = date.hour as u32 + minutes / 60;
+        date.hour = (hours % 24) as u8;
+        // Less than a day carries into the next day at most
+        if hours < 24 {
+            return date;
+        }
+        if date.day < date.days_in_month() {
+            date.day += 1;
+        } else if date.month < 12 {
+            date.day = 1;
+            date.month += 1;
+        } else if date.year < LAST_YEAR {
+            date = DateTime { year: date.year + 1, month: 1, day: 1, ..date };
+        } else {
+            return LAST;
+        }
+        date
+    }
+
+    /// Adds a duration, ignoring fractions of a second
+    /// returns None past the end of LAST_YEAR
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        let seconds = u32::try_from(duration.as_secs()).ok()?;
+        DateTime::from_seconds(self.to_seconds().checked_add(seconds)?)
+    }
+
+    /// Subtracts a duration, ignoring fractions of a second
+    /// returns None before the start of FIRST_YEAR
+    pub fn checked_sub(self, duration: Duration) -> Option<Self> {
+        let seconds = u32::try_from(duration.as_secs()).ok()?;
+        DateTime::from_seconds(self.to_seconds().checked_sub(seconds)?)
+    }
+
+    /// returns the seconds since the start of FIRST_YEAR
+    fn to_seconds(self) -> u32 {
+        let days = (FIRST_YEAR..self.year).map(days_in_year).sum::<u32>()
+            + (1..self.month).map(|month| days_in_month(month, self.year) as u32).sum::<u32>()
+            + self.day as u32
+            - 1;
+        days * DAY_SECONDS + self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32
+    }
+
+    /// param seconds: Seconds since the start of FIRST_YEAR
+    /// returns the DateTime, None past the end of LAST_YEAR
+    fn from_seconds(seconds: u32) -> Option<Self> {
+        let mut days = seconds / DAY_SECONDS;
+        let time = seconds % DAY_SECONDS;
+        let mut year = FIRST_YEAR;
+        while days >= days_in_year(year) {
+            days -= days_in_year(year);
+            year += 1;
+        }
+        let mut month = 1;
+        while days >= days_in_month(month, year) as u32 {
+            days -= days_in_month(month, year) as u32;
+            month += 1;
+        }
+        DateTime::new(year, month, days as u8 + 1, (time / 3600) as u8, (time / 60 % 60) as u8, (time % 60) as u8)
+    }
+}
+
+impl Add<Duration> for DateTime {
+    type Output = DateTime;
+
+    /// Adds a duration, the clock stops at the end of LAST_YEAR
+    fn add(self, duration: Duration) -> DateTime {
+        self.checked_add(duration).unwrap_or(LAST)
+    }
+}
+
+impl Sub<Duration> for DateTime {
+    type Output = DateTime;
+
+    /// Subtracts a duration, stopping at the start of FIRST_YEAR
+    fn sub(self, duration: Duration) -> DateTime {
+        self.checked_sub(duration).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+        DateTime::new(year, month, day, hour, minute, second).unwrap()
+    }
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        assert_eq!(DateTime::new(2023, 2, 29, 0, 0, 0), None);
+        assert_eq!(DateTime::new(2024, 13, 1, 0, 0, 0), None);
+        assert_eq!(DateTime::new(2024, 0, 1, 0, 0, 0), None);
+        assert_eq!(DateTime::new(2024, 4, 0, 0, 0, 0), None);
+        assert_eq!(DateTime::new(2024, 4, 1, 24, 0, 0), None);
+        assert_eq!(DateTime::new(2100, 1, 1, 0, 0, 0), None);
+        assert!(DateTime::new(2024, 2, 29, 23, 59, 59).is_some());
+    }
+
+    #[test]
+    fn adding_rolls_over_minutes_and_hours() {
+        assert_eq!(date(2024, 6, 5, 10, 59, 59) + SECOND, date(2024, 6, 5, 11, 0, 0));
+    }
+
+    #[test]
+    fn adding_rolls_over_into_next_month() {
+        assert_eq!(date(2024, 4, 30, 23, 59, 59) + SECOND, date(2024, 5, 1, 0, 0, 0));
+        assert_eq!(date(2024, 2, 28, 12, 0, 0) + Duration::from_secs(24 * 3600), date(2024, 2, 29, 12, 0, 0));
+    }
+
+    #[test]
+    fn adding_rolls_over_into_next_year() {
+        assert_eq!(date(2023, 12, 31, 23, 59, 59) + SECOND, date(2024, 1, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn ticking_matches_adding_a_duration() {
+        let dates = [date(2024, 6, 5, 10, 59, 59), date(2024, 2, 28, 23, 0, 1), date(2023, 12, 31, 23, 59, 59), date(LAST_YEAR, 12, 31, 23, 0, 0)];
+        for date in dates {
+            for seconds in [0, 1, 59, 3600, 5000, DAY_SECONDS - 1, DAY_SECONDS, 40 * DAY_SECONDS] {
+                assert_eq!(date.add_seconds(seconds), date + Duration::from_secs(seconds as u64), "{:?} + {}", date, seconds);
+            }
+        }
+    }
+
+    #[test]
+    fn subtracting_goes_back_across_months() {
+        assert_eq!(date(2024, 3, 1, 0, 0, 30) - Duration::from_secs(60), date(2024, 2, 29, 23, 59, 30));
+        assert_eq!(DateTime::default() - SECOND, DateTime::default());
+        assert_eq!(DateTime::default().checked_sub(SECOND), None);
+    }
+
+    #[test]
+    fn the_clock_stops_at_the_end_of_the_last_year() {
+        let last = date(LAST_YEAR, 12, 31, 23, 59, 59);
+        assert_eq!(last + SECOND, last);
+        assert_eq!(last.checked_add(SECOND), None);
+    }
+
+    #[test]
+    fn dates_are_compared_in_time() {
+        assert!(date(2024, 1, 31, 0, 0, 0) < date(2024, 2, 1, 0, 0, 0));
+        assert!(date(2023, 12, 31, 23, 59, 59) < date(2024, 1, 1, 0, 0, 0));
+        assert!(date(2024, 5, 5, 10, 0, 1) > date(2024, 5, 5, 10, 0, 0));
+    }
+
+    #[test]
+    fn february_respects_leap_years() {
+        assert_eq!(date(2024, 2, 1, 0, 0, 0).days_in_month(), 29);
+        assert_eq!(date(2023, 2, 1, 0, 0, 0).days_in_month(), 28);
+        assert_eq!(days_in_month(2, 1900), 28);
+        assert_eq!(date(2000, 2, 1, 0, 0, 0).days_in_month(), 29);
+    }
+
+    #[test]
+    fn weekdays_are_counted_from_monday() {
+        assert_eq!(weekday(1, 1, 2000), 5);
+        assert_eq!(weekday(29, 2, 2024), 3);
+        assert_eq!(weekday(1, 3, 2024), 4);
+        assert_eq!(weekday(31, 12, 2023), 6);
+        assert_eq!(weekday(1, 1, 2024), 0);
+        assert_eq!(date(2024, 7, 15, 0, 0, 0).weekday(), 0);
+    }
+}
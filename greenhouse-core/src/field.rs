@@ -0,0 +1,104 @@
+//! Numeric field editor used by every settings screen
+//!
+//! A Field describes the range of a value and how it changes when UP or DOWN is pressed,
+//! so bounds are enforced the same way for thresholds, dates and schedules.
+
+/// Range and stepping of an editable value
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Field {
+    pub min: i32,
+    pub max: i32,
+    /// Continue at the other bound instead of stopping at the bounds
+    pub wrap: bool,
+    /// Change per button press
+    pub step: i32,
+}
+
+impl Field {
+    /// Creates a field that stops at its bounds and changes by 1
+    /// param min: Smallest allowed value
+    /// param max: Largest allowed value
+    pub const fn new(min: i32, max: i32) -> Self {
+        Field { min, max, wrap: false, step: 1 }
+    }
+
+    /// Makes the field wrap around at its bounds (e.g. 59 -> 0 for minutes)
+    pub const fn wrapping(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+
+    /// Sets the change per button press
+    /// param step: The change
+    pub const fn with_step(mut self, step: i32) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Brings a value into the range
+    /// param value: The value
+    pub fn clamp(&self, value: i32) -> i32 {
+        value.clamp(self.min, self.max)
+    }
+
+    /// Moves a value by a number of steps
+    /// Values outside of the range are brought into it first
+    /// param value: The current value
+    /// param steps: Signed number of steps
+    /// returns the new value, which lies within min and max
+    pub fn apply(&self, value: i32, steps: i32) -> i32 {
+        let target = self.clamp(value) + steps * self.step;
+        if self.wrap {
+            let span = self.max - self.min + 1;
+            self.min + (target - self.min).rem_euclid(span)
+        } else {
+            target.clamp(self.min, self.max)
+        }
+    }
+}
+
+/// Gets the amount of steps a button press moves a field
+/// Holding a button down makes the steps grow, so large ranges can be crossed quickly
+/// param repeats: The amount of presses in a row without releasing the button
+pub fn acceleration(repeats: u8) -> i32 {
+    match repeats {
+        0..=3 => 1,
+        4..=7 => 5,
+        _ => 10,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_fields_stop_at_their_limits() {
+        let humidity = Field::new(0, 100);
+        assert_eq!(humidity.apply(100, 1), 100);
+        assert_eq!(humidity.apply(2, -5), 0);
+        assert_eq!(humidity.apply(150, -1), 99);
+    }
+
+    #[test]
+    fn wrapping_fields_continue_at_the_other_bound() {
+        let day = Field::new(1, 31).wrapping();
+        assert_eq!(day.apply(31, 1), 1);
+        assert_eq!(day.apply(1, -1), 31);
+        assert_eq!(Field::new(0, 59).wrapping().apply(55, 10), 5);
+    }
+
+    #[test]
+    fn steps_are_scaled() {
+        let minutes = Field::new(0, 59).wrapping().with_step(15);
+        assert_eq!(minutes.apply(0, 1), 15);
+        assert_eq!(minutes.apply(0, -1), 45);
+    }
+
+    #[test]
+    fn holding_a_button_accelerates() {
+        assert_eq!(acceleration(0), 1);
+        assert_eq!(acceleration(5), 5);
+        assert_eq!(acceleration(u8::MAX), 10);
+    }
+}
@@ -0,0 +1,114 @@
+//! Fire suppression state machine
+//!
+//! Smoke latches the fire state. The sprinklers keep running until no smoke has been
+//! detected for MIN_SPRINKLER_MS and someone acknowledged the alarm with a button,
+//! so a detector that flickers on and off can't stop the sprinklers early.
+
+/// Time the sprinklers keep running after smoke was last detected
+pub const MIN_SPRINKLER_MS: u32 = 5 * 60 * 1000;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FireState {
+    Clear,
+    Active {
+        /// When smoke was last detected
+        last_smoke: u32,
+        /// If the alarm was silenced by a button press
+        acknowledged: bool,
+        /// If the roof vent was open before the fire
+        roof_open: bool,
+    },
+}
+
+/// Entry and exit actions the controller has to carry out
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transition {
+    /// Enable the sprinklers, close the roof vent and sound the alarm
+    Enter,
+    /// Disable the sprinklers and alarm and reopen the roof vent if it was open
+    Exit { roof_open: bool },
+}
+
+impl FireState {
+    /// Advances the state machine
+    /// param smoke: If the smoke detector is triggered
+    /// param now: Milliseconds since boot
+    /// param roof_open: If the roof vent is currently open
+    /// returns the actions to carry out, if the state changed
+    pub fn update(&mut self, smoke: bool, now: u32, roof_open: bool) -> Option<Transition> {
+        match self {
+            FireState::Clear if smoke => {
+                *self = FireState::Active { last_smoke: now, acknowledged: false, roof_open };
+                Some(Transition::Enter)
+            }
+            FireState::Clear => None,
+            FireState::Active { last_smoke, .. } if smoke => {
+                *last_smoke = now;
+                None
+            }
+            FireState::Active { last_smoke, acknowledged, roof_open } => {
+                if *acknowledged && now.wrapping_sub(*last_smoke) >= MIN_SPRINKLER_MS {
+                    let roof_open = *roof_open;
+                    *self = FireState::Clear;
+                    Some(Transition::Exit { roof_open })
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Silences the alarm
+    /// The fire state is left once the sprinklers ran long enough
+    pub fn acknowledge(&mut self) {
+        if let FireState::Active { acknowledged, .. } = self {
+            *acknowledged = true;
+        }
+    }
+
+    /// returns if there is a fire being handled
+    pub fn is_active(&self) -> bool {
+        matches!(self, FireState::Active { .. })
+    }
+
+    /// returns if the alarm should be sounding
+    pub fn is_alarm_on(&self) -> bool {
+        matches!(self, FireState::Active { acknowledged: false, .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smoke_latches_until_acknowledged() {
+        let mut fire = FireState::Clear;
+        assert_eq!(fire.update(true, 0, true), Some(Transition::Enter));
+        assert_eq!(fire.update(false, MIN_SPRINKLER_MS * 2, false), None);
+        assert!(fire.is_alarm_on());
+
+        fire.acknowledge();
+        assert!(!fire.is_alarm_on());
+        assert_eq!(fire.update(false, MIN_SPRINKLER_MS * 2, false), Some(Transition::Exit { roof_open: true }));
+        assert_eq!(fire, FireState::Clear);
+    }
+
+    #[test]
+    fn sprinklers_run_for_the_minimum_time_after_the_last_smoke() {
+        let mut fire = FireState::Clear;
+        fire.update(true, 1000, false);
+        fire.acknowledge();
+        fire.update(true, 5000, false);
+        assert_eq!(fire.update(false, 4999 + MIN_SPRINKLER_MS, false), None);
+        assert_eq!(fire.update(false, 5000 + MIN_SPRINKLER_MS, false), Some(Transition::Exit { roof_open: false }));
+    }
+
+    #[test]
+    fn acknowledging_without_a_fire_does_nothing() {
+        let mut fire = FireState::Clear;
+        fire.acknowledge();
+        assert_eq!(fire, FireState::Clear);
+        assert_eq!(fire.update(false, 0, false), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Hardware abstraction traits used by the controller.
+//!
+//! Pins implementing the `embedded-hal` digital traits get these for free,
+//! everything else (sensors, mocks, simulated hardware) implements them directly.
+
+use crate::climate::ClimateData;
+use crate::datetime::DateTime;
+use embedded_hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin};
+
+/// Something that can be switched on and off (sprinklers, roof vent, buzzer, etc.)
+pub trait Actuator {
+    /// Switches the actuator
+    /// param on: If the actuator should be enabled
+    fn set_on(&mut self, on: bool);
+
+    /// returns if the actuator is currently enabled
+    fn is_on(&self) -> bool;
+}
+
+impl<P: OutputPin + StatefulOutputPin> Actuator for P {
+    fn set_on(&mut self, on: bool) {
+        // Errors are impossible on the AVR pins, so they are ignored
+        let _ = if on { self.set_high() } else { self.set_low() };
+    }
+
+    fn is_on(&self) -> bool {
+        self.is_set_high().unwrap_or(false)
+    }
+}
+
+/// A servo or H-bridge that holds the roof vent at a position
+pub trait Servo {
+    /// Moves the drive
+    /// param position: Percent of the drive range
+    fn set_position(&mut self, position: u8);
+
+    /// Reads the position feedback
+    /// returns the position in percent of the drive range, None without feedback
+    fn feedback(&mut self) -> Option<u8>;
+}
+
+/// A push button
+pub trait Button {
+    /// returns if the button is currently held down
+    fn is_pressed(&self) -> bool;
+}
+
+impl<P: InputPin> Button for P {
+    fn is_pressed(&self) -> bool {
+        self.is_high().unwrap_or(false)
+    }
+}
+
+/// A smoke detector
+pub trait SmokeSensor {
+    /// returns if smoke is currently detected
+    fn is_smoke_detected(&self) -> bool;
+}
+
+impl<P: InputPin> SmokeSensor for P {
+    fn is_smoke_detected(&self) -> bool {
+        self.is_high().unwrap_or(false)
+    }
+}
+
+/// A temperature, humidity and air pressure sensor
+pub trait ClimateSensor {
+    type Error;
+
+    /// Prepares the sensor for the next measurement
+    /// This should be called before getting data
+    fn prepare(&mut self) -> Result<(), Self::Error>;
+
+    /// Reads the latest measurement
+    fn measure(&mut self) -> Result<ClimateData, Self::Error>;
+
+    /// Frees the bus and sets the sensor up again after a measurement failed
+    /// Sensors that can't get stuck have nothing to do
+    fn recover(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A character display (the LCD 1602 on the board)
+pub trait Display {
+    /// Clears the display
+    fn clean_display(&mut self);
+
+    /// Moves the cursor
+    /// param pos: (column, row) starting at the top left
+    fn set_cursor_pos(&mut self, pos: (u8, u8));
+
+    /// Writes text starting at the cursor
+    fn write_str_to_cur(&mut self, line: &str);
+
+    /// Enables or disables the blinking cursor
+    fn set_cursor_blink(&mut self, on: bool);
+}
+
+/// Byte addressable non-volatile memory (the EEPROM of the ATmega328P)
+pub trait Storage {
+    /// Reads a single byte
+    /// param address: Offset from the start of the memory
+    fn read_byte(&self, address: u16) -> u8;
+
+    /// Writes a single byte
+    /// param address: Offset from the start of the memory
+    /// param value: The byte to store
+    fn write_byte(&mut self, address: u16, value: u8);
+}
+
+/// A battery backed real-time clock
+pub trait RealTimeClock {
+    type Error;
+
+    /// Reads the current date
+    fn get_date(&mut self) -> Result<DateTime, Self::Error>;
+
+    /// Sets the current date
+    fn set_date(&mut self, date: DateTime) -> Result<(), Self::Error>;
+}
+
+/// A free running millisecond counter started at boot
+pub trait Monotonic {
+    /// Gets the milliseconds since boot
+    /// The counter wraps after ~49 days, so compare values with `wrapping_sub`
+    fn millis(&self) -> u32;
+}
@@ -0,0 +1,199 @@
+//! Fault detection for the climate sensor
+//!
+//! A failed measurement is retried right away, and the bus is recovered once a whole poll
+//! failed. Values the BME680 can't measure count as a failure too, and so do values that
+//! didn't change at all for STALE_MS: the raw readings of a working sensor always jitter,
+//! while a sensor that dropped out of forced mode keeps returning its last measurement.
+//!
+//! A single failed poll is not a fault, the controller keeps acting on the last good
+//! reading. Only MAX_FAILED_POLLS in a row make the sensor faulty, and the next good
+//! reading clears the fault again.
+
+use crate::climate::ClimateData;
+use crate::hal::ClimateSensor;
+
+/// Measurements tried within one poll
+pub const ATTEMPTS: u8 = 3;
+/// Failed polls in a row before the sensor is considered faulty
+pub const MAX_FAILED_POLLS: u8 = 5;
+/// A sensor reporting the exact same values for this long is stuck
+pub const STALE_MS: u32 = 10 * 60 * 1000;
+
+/// Why the climate sensor is not trusted
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SensorFault {
+    /// Measurements keep failing, even after recovering the bus
+    NoResponse,
+    /// The values are outside of what the sensor can measure
+    Implausible,
+    /// The values stopped changing
+    Stale,
+}
+
+impl SensorFault {
+    /// returns the description shown on the LCD
+    pub fn label(&self) -> &'static str {
+        match self {
+            SensorFault::NoResponse => "No response",
+            SensorFault::Implausible => "Bad values",
+            SensorFault::Stale => "Values stuck",
+        }
+    }
+}
+
+/// Tracks the failures of the climate sensor
+#[derive(Default)]
+pub struct SensorHealth {
+    /// Polls that failed in a row
+    failures: u8,
+    /// The last good measurement and since when it was reported unchanged
+    last: Option<(ClimateData, u32)>,
+    fault: Option<SensorFault>,
+}
+
+impl SensorHealth {
+    /// Measures the climate, retrying and recovering the bus on failures
+    /// param sensor: Climate sensor instance
+    /// param now: Milliseconds since boot
+    /// returns the measurement, None if this poll failed
+    pub fn poll<S: ClimateSensor>(&mut self, sensor: &mut S, now: u32) -> Option<ClimateData> {
+        match self.check(measure(sensor), now) {
+            Ok(data) => {
+                self.failures = 0;
+                self.fault = None;
+                Some(data)
+            }
+            Err(fault) => {
+                // The sensor is set up again, whatever went wrong; it is only retried next poll
+                let _ = sensor.recover();
+                self.failures = self.failures.saturating_add(1);
+                if self.failures >= MAX_FAILED_POLLS {
+                    self.fault = Some(fault);
+                }
+                None
+            }
+        }
+    }
+
+    /// returns why the sensor is faulty, None while it can be trusted
+    pub fn fault(&self) -> Option<SensorFault> {
+        self.fault
+    }
+
+    /// Validates a measurement
+    /// param data: The measurement, None if the sensor didn't answer
+    /// param now: Milliseconds since boot
+    /// returns the measurement or what is wrong with it
+    fn check(&mut self, data: Option<ClimateData>, now: u32) -> Result<ClimateData, SensorFault> {
+        let data = data.ok_or(SensorFault::NoResponse)?;
+        if !is_plausible(&data) {
+            return Err(SensorFault::Implausible);
+        }
+        match self.last {
+            Some((last, since)) if last == data => {
+                if now.wrapping_sub(since) >= STALE_MS {
+                    return Err(SensorFault::Stale);
+                }
+            }
+            _ => self.last = Some((data, now)),
+        }
+        Ok(data)
+    }
+}
+
+/// Prepares the sensor and reads it, up to ATTEMPTS times
+/// param sensor: Climate sensor instance
+/// returns the measurement, None if every attempt failed
+fn measure<S: ClimateSensor>(sensor: &mut S) -> Option<ClimateData> {
+    (0..ATTEMPTS).find_map(|_| sensor.prepare().and_then(|_| sensor.measure()).ok())
+}
+
+/// Checks a measurement against the operating range of the BME680
+/// param data: The measurement
+/// returns false for values the sensor can't report, including NaN
+pub fn is_plausible(data: &ClimateData) -> bool {
+    (-40. ..=85.).contains(&data.temperature_celsius) && (0. ..=100.).contains(&data.humidity_percent) && (300. ..=1100.).contains(&data.pressure_hpa)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALM: ClimateData = ClimateData { temperature_celsius: 20., humidity_percent: 65., pressure_hpa: 1013., gas_resistance_ohm: None };
+
+    /// Answers after failing a given number of times, and counts the bus recoveries
+    struct FlakySensor {
+        data: ClimateData,
+        failures: u8,
+        recoveries: u8,
+    }
+
+    impl ClimateSensor for FlakySensor {
+        type Error = ();
+
+        fn prepare(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn measure(&mut self) -> Result<ClimateData, ()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(());
+            }
+            Ok(self.data)
+        }
+
+        fn recover(&mut self) -> Result<(), ()> {
+            self.recoveries += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failed_measurements_are_retried_within_a_poll() {
+        let mut sensor = FlakySensor { data: CALM, failures: ATTEMPTS - 1, recoveries: 0 };
+        let mut health = SensorHealth::default();
+        assert_eq!(health.poll(&mut sensor, 0), Some(CALM));
+        assert_eq!(sensor.recoveries, 0);
+
+        sensor.failures = ATTEMPTS;
+        assert_eq!(health.poll(&mut sensor, 1000), None);
+        assert_eq!(sensor.recoveries, 1);
+        assert_eq!(health.fault(), None);
+    }
+
+    #[test]
+    fn a_silent_sensor_becomes_faulty_and_recovers() {
+        let mut sensor = FlakySensor { data: CALM, failures: u8::MAX, recoveries: 0 };
+        let mut health = SensorHealth::default();
+        for poll in 0..MAX_FAILED_POLLS as u32 {
+            assert_eq!(health.fault(), None);
+            assert_eq!(health.poll(&mut sensor, poll * 1000), None);
+        }
+        assert_eq!(health.fault(), Some(SensorFault::NoResponse));
+        assert_eq!(sensor.recoveries, MAX_FAILED_POLLS);
+
+        sensor.failures = 0;
+        assert_eq!(health.poll(&mut sensor, 10_000), Some(CALM));
+        assert_eq!(health.fault(), None);
+    }
+
+    #[test]
+    fn implausible_and_stuck_values_are_rejected() {
+        assert!(!is_plausible(&ClimateData { temperature_celsius: f32::NAN, ..CALM }));
+        assert!(!is_plausible(&ClimateData { humidity_percent: 100.5, ..CALM }));
+        assert!(!is_plausible(&ClimateData::default()));
+
+        let mut sensor = FlakySensor { data: CALM, failures: 0, recoveries: 0 };
+        let mut health = SensorHealth::default();
+        assert_eq!(health.poll(&mut sensor, 0), Some(CALM));
+        assert_eq!(health.poll(&mut sensor, STALE_MS - 1), Some(CALM));
+        for poll in 0..MAX_FAILED_POLLS as u32 {
+            assert_eq!(health.poll(&mut sensor, STALE_MS + poll * 1000), None);
+        }
+        assert_eq!(health.fault(), Some(SensorFault::Stale));
+
+        sensor.data.temperature_celsius = 20.01;
+        assert_eq!(health.poll(&mut sensor, STALE_MS + 10_000), Some(sensor.data));
+    }
+}
@@ -0,0 +1,28 @@
+//! Hardware independent logic of the greenhouse monitor.
+//!
+//! Everything in here builds for the AVR firmware as well as for the host,
+//! so the calendar, schedule and climate rules can be tested with `cargo test`.
+#![no_std]
+
+pub mod air;
+pub mod arbiter;
+pub mod buttons;
+pub mod bus;
+pub mod climate;
+pub mod controller;
+pub mod datetime;
+pub mod field;
+pub mod fire;
+pub mod hal;
+pub mod health;
+pub mod menu;
+pub mod pid;
+pub mod preferences;
+pub mod relay;
+pub mod rtc;
+pub mod schedule;
+pub mod screens;
+pub mod storage;
+pub mod vent;
+
+pub use preferences::Preferences;
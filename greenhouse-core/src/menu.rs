@@ -0,0 +1,239 @@
+//! Screens and the editing of the Preferences from them
+//!
+//! Every screen implements the Screen trait and is listed in `screens::SCREENS`, or in
+//! `screens::SERVICE_SCREENS` if it belongs to the hidden service menu.
+//! Editing is a state machine that is advanced by at most one button press per
+//! main loop tick, so the controller never waits for the user and keeps polling the
+//! sensors, driving the vent and sprinklers and watching for smoke while the menu is open.
+
+use crate::arbiter::{Outputs, OUTPUTS};
+use crate::climate::Reading;
+use crate::datetime::DateTime;
+use crate::field::{acceleration, Field};
+use crate::hal::Display;
+use crate::Preferences;
+use heapless::String;
+
+/// A button press handed to the editor
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Input {
+    Up,
+    Down,
+    Select,
+    /// Up and Down pressed together
+    UpDown,
+}
+
+/// The state of the menu besides the Preferences
+/// It is kept by the controller and never stored, a reset starts over from the defaults
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Session {
+    /// The date being set on the date screen, and if it should be applied
+    pub date_draft: (DateTime, bool),
+    /// If a field of the date draft was changed since the date screen was opened
+    pub date_edited: bool,
+    /// The schedule picked on the watering screen
+    pub watering_slot: u8,
+    /// If the relay auto-tune of the PID gains was requested, the controller clears it once tuning is over
+    pub autotune: bool,
+    /// Manual overrides of every Output, None follows the rules
+    /// A reset always returns to automatic control
+    pub overrides: [Option<bool>; OUTPUTS.len()],
+}
+
+/// A page of the menu
+/// Screens without editable fields are only displayed
+pub trait Screen: Sync {
+    /// Renders the screen while browsing
+    /// param reading: The latest measurements, None until the sensor answered
+    /// param outputs: The state of the actuators and why
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    /// param lcd: LCD instance
+    fn render(&self, reading: Option<&Reading>, outputs: &Outputs, preferences: &Preferences, session: &Session, lcd: &mut dyn Display);
+
+    /// Called when UP or DOWN is pressed while browsing, for screens with several pages
+    /// param next: If UP was pressed
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    /// returns if the screen turned a page, otherwise the neighbouring screen is shown
+    fn on_browse(&self, _next: bool, _preferences: &Preferences, _session: &mut Session) -> bool {
+        false
+    }
+
+    /// The amount of fields that are edited one after another when SELECT is pressed
+    fn fields(&self) -> u8 {
+        0
+    }
+
+    /// Called when SELECT opens the editor
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    fn on_select(&self, _preferences: &mut Preferences, _session: &mut Session) {}
+
+    /// Gets the range and stepping of a field
+    /// param field: Index of the field
+    /// param preferences: Client Preferences, for ranges that depend on other values
+    /// param session: State of the menu
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        Field::new(0, 0)
+    }
+
+    /// Gets the value of a field
+    /// param field: Index of the field
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    fn value(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> i32 {
+        0
+    }
+
+    /// Stores the edited value of a field
+    /// param field: Index of the field
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    /// param value: The new value, within the range of the field
+    fn set_value(&self, _field: u8, _preferences: &mut Preferences, _session: &mut Session, _value: i32) {}
+
+    /// Renders the field being edited
+    /// param field: Index of the field
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    /// param lcd: LCD instance
+    fn render_field(&self, _field: u8, _preferences: &Preferences, _session: &Session, _lcd: &mut dyn Display) {}
+
+    /// Called when UP and DOWN are pressed together while editing
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    /// returns None to keep editing, otherwise if the changes are kept
+    fn on_chord(&self, _preferences: &mut Preferences, _session: &mut Session) -> Option<bool> {
+        None
+    }
+
+    /// Called after the last field was confirmed, e.g. to check legality
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    /// returns if the changes are kept
+    fn on_confirm(&self, _preferences: &mut Preferences, _session: &mut Session) -> bool {
+        true
+    }
+
+    /// If the real-time clock has to be set once editing is done
+    fn sets_clock(&self) -> bool {
+        false
+    }
+
+    /// Gets the servo position to hold while a field is edited, for calibrating the vent
+    /// param field: Index of the field
+    /// param preferences: Client Preferences
+    /// returns the position in percent of the drive range, None to keep controlling the vent
+    fn calibration_position(&self, _field: u8, _preferences: &Preferences) -> Option<u8> {
+        None
+    }
+}
+
+/// The editor after a button press
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Edit {
+    /// Editing goes on
+    Open(EditState),
+    /// Editing ended, committed if the changes are kept and have to be stored
+    Done { committed: bool },
+}
+
+/// The edit cursor
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EditState {
+    /// Index of the field being edited
+    pub field: u8,
+    /// Presses of UP or DOWN in a row while the button is held
+    repeats: u8,
+}
+
+impl EditState {
+    /// Opens the editor of a screen
+    /// param screen: The screen SELECT was pressed on
+    /// param preferences: Client Preferences
+    /// param session: State of the menu
+    /// returns None if the screen has nothing to configure
+    pub fn enter(screen: &dyn Screen, preferences: &mut Preferences, session: &mut Session) -> Option<EditState> {
+        if screen.fields() == 0 {
+            return None;
+        }
+        screen.on_select(preferences, session);
+        Some(EditState { field: 0, repeats: 0 })
+    }
+
+    /// Applies a button press to the Preferences
+    /// param screen: The screen being edited
+    /// param input: The button that was pressed
+    /// param held: If the button was held down since the previous press
+    /// param preferences: The Preferences being edited
+    /// param session: State of the menu
+    /// returns the next edit step, or if the changes are kept once editing is finished
+    pub fn handle(mut self, screen: &dyn Screen, input: Input, held: bool, preferences: &mut Preferences, session: &mut Session) -> Edit {
+        let direction = match input {
+            Input::UpDown => {
+                return match screen.on_chord(preferences, session) {
+                    Some(committed) => Edit::Done { committed },
+                    None => Edit::Open(self),
+                }
+            }
+            Input::Up => 1,
+            Input::Down => -1,
+            Input::Select if self.field + 1 < screen.fields() => return Edit::Open(EditState { field: self.field + 1, repeats: 0 }),
+            Input::Select => return Edit::Done { committed: screen.on_confirm(preferences, session) },
+        };
+
+        self.repeats = if held { self.repeats.saturating_add(1) } else { 0 };
+        let field = screen.field(self.field, preferences, session);
+        let value = field.apply(screen.value(self.field, preferences, session), direction * acceleration(self.repeats));
+        screen.set_value(self.field, preferences, session, value);
+        Edit::Open(self)
+    }
+
+    /// Renders the field being edited
+    /// param screen: The screen being edited
+    /// param preferences: The Preferences being edited
+    /// param session: State of the menu
+    /// param lcd: LCD instance
+    pub fn render(&self, screen: &dyn Screen, preferences: &Preferences, session: &Session, lcd: &mut dyn Display) {
+        screen.render_field(self.field, preferences, session, lcd);
+    }
+}
+
+/// Renders the Preferences on screen with a blinking indicator cursor
+/// param line: The preferences line
+/// param left_cursor: If the lower bound is selected
+/// param lcd: LCD instance
+pub fn render_edit_screen<const N: usize, L: Display + ?Sized>(line: &String<N>, left_cursor: bool, lcd: &mut L) {
+    // Clear
+    lcd.clean_display();
+
+    // Write top info
+    lcd.set_cursor_pos((0, 0));
+    lcd.write_str_to_cur(line);
+
+    // Create bottom blinking cursor
+    if left_cursor {
+        lcd.set_cursor_pos((0, 1));
+    } else {
+        lcd.set_cursor_pos((15, 1));
+    }
+    lcd.set_cursor_blink(true);
+}
+
+/// Renders the field being edited (min, hr, duration, etc.) on the first line with a central blinking cursor on the second line
+/// param line: The field line
+/// param lcd: LCD instance
+pub fn render_field_edit_screen<const N: usize, L: Display + ?Sized>(line: &String<N>, lcd: &mut L) {
+    // Clear
+    lcd.clean_display();
+
+    // Write field
+    lcd.set_cursor_pos((0, 0));
+    lcd.write_str_to_cur(line);
+
+    // Create blinking cursor
+    lcd.set_cursor_pos((7, 1));
+    lcd.set_cursor_blink(true);
+}
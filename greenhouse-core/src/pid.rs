@@ -0,0 +1,363 @@
+//! Fixed-point PID temperature control and relay auto-tuning
+//!
+//! Everything is integer math, so the controller runs on the AVR without soft-float.
+//! Temperatures are in tenths of a degree Celsius and the output is in hundredths
+//! of a percent internally, whole percent outside. The gains are stored in hundredths:
+//! Kp in percent per degree, Ki in percent per degree and minute, and Kd in percent
+//! per degree per minute of change.
+//!
+//! Heaters and vents without a servo can only be switched on and off, so the output is
+//! turned into on and off times within a repeating window (time proportioning).
+
+/// Kp, Ki, Kd (Hundredths, see the module documentation)
+pub type Gains = (u16, u16, u16);
+/// Highest gain that can be set, the auto-tune limits its results to it as well
+pub const MAX_GAIN: u16 = 20000;
+
+/// Window the output of on/off actuators is spread over
+/// Long, so pulses stay above the minimum run times of the heater
+pub const PWM_WINDOW_MS: u32 = 600_000;
+/// Longer gaps between updates are treated as this long, so a stall can't wind up the integral
+const MAX_DT_MS: u32 = 10_000;
+/// Output changes in hundredths of a percent per unit of the gains and temperature
+const HUNDREDTHS: i64 = 100;
+
+/// The actuator driven by the PID controller
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PidMode {
+    /// The on/off rules drive every actuator
+    Off,
+    /// The heater holds the lower temperature threshold
+    Heater,
+    /// The roof vent holds the upper temperature threshold
+    Vent,
+}
+
+impl PidMode {
+    /// param value: The stored value
+    /// returns the mode, Off for unknown values
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => PidMode::Heater,
+            2 => PidMode::Vent,
+            _ => PidMode::Off,
+        }
+    }
+
+    /// returns the name shown on the LCD
+    pub fn label(&self) -> &'static str {
+        match self {
+            PidMode::Off => "Off",
+            PidMode::Heater => "Heater",
+            PidMode::Vent => "Vent",
+        }
+    }
+
+    /// If more output lowers the temperature
+    pub fn is_cooling(&self) -> bool {
+        *self == PidMode::Vent
+    }
+}
+
+/// State of the PID controller between updates
+#[derive(Default)]
+pub struct Pid {
+    /// Integral term in thousandths of the output unit, for precision at slow updates
+    integral: i32,
+    /// Previous measurement and when it was taken
+    last: Option<(i16, u32)>,
+}
+
+impl Pid {
+    /// Forgets the integral and the previous measurement
+    pub fn reset(&mut self) {
+        *self = Pid::default();
+    }
+
+    /// Calculates the output for a new measurement
+    /// The derivative acts on the measurement, so setpoint changes don't kick the output
+    /// param setpoint: Target temperature in tenths of a degree
+    /// param measurement: Measured temperature in tenths of a degree
+    /// param cooling: If more output lowers the temperature
+    /// param now: Milliseconds since boot
+    /// param gains: Kp, Ki, Kd
+    /// param limits: Min and Max output in percent
+    /// returns the output in percent, within the limits
+    pub fn update(&mut self, setpoint: i16, measurement: i16, cooling: bool, now: u32, gains: Gains, limits: (u8, u8)) -> u8 {
+        let sign = if cooling { -1 } else { 1 };
+        let error = sign * (setpoint as i64 - measurement as i64);
+        let (min, max) = (limits.0 as i64 * HUNDREDTHS, limits.1 as i64 * HUNDREDTHS);
+
+        let (dt, change) = match self.last {
+            Some((last, at)) => (now.wrapping_sub(at).clamp(1, MAX_DT_MS) as i64, sign * (measurement as i64 - last as i64)),
+            None => (0, 0),
+        };
+        self.last = Some((measurement, now));
+
+        let proportional = gains.0 as i64 * error / 10;
+        let derivative = if dt > 0 { -(gains.2 as i64) * change * 6_000 / dt } else { 0 };
+        let integral = (self.integral as i64 + gains.1 as i64 * error * dt / 600).clamp(min * 1000, max * 1000);
+
+        let unclamped = proportional + integral / 1000 + derivative;
+        let output = unclamped.clamp(min, max);
+        // Anti-windup: stop integrating while the output is saturated in the direction of the error
+        let winding_up = (unclamped > max && error > 0) || (unclamped < min && error < 0);
+        if !winding_up {
+            self.integral = integral as i32;
+        }
+        (output / HUNDREDTHS) as u8
+    }
+}
+
+/// Spreads an output over a repeating window for actuators that can only switch
+/// param output: Output in percent
+/// param now: Milliseconds since boot
+/// returns if the actuator should be on right now
+pub fn time_proportion(output: u8, now: u32) -> bool {
+    now % PWM_WINDOW_MS < PWM_WINDOW_MS / 100 * output.min(100) as u32
+}
+
+/// Limits an output to pulses and gaps an actuator with minimum run and rest times can follow
+/// The relay would stretch shorter ones, so they are rounded to the minimum or to fully off or on
+/// param output: Output in percent
+/// param timing: Min on, Min off time in seconds
+/// returns the output to time proportion in percent
+pub fn pulse_duty(output: u8, timing: (u16, u16)) -> u8 {
+    let percent = |seconds: u16| (seconds as u32 * 100_000).div_ceil(PWM_WINDOW_MS).min(100) as u8;
+    let (min_on, min_off) = (percent(timing.0), percent(timing.1));
+    let output = output.min(100);
+    if output < min_on {
+        if output * 2 < min_on { 0 } else { min_on }
+    } else if 100 - output < min_off {
+        if (100 - output) * 2 < min_off { 100 } else { 100 - min_off }
+    } else {
+        output
+    }
+}
+
+/// Full oscillations needed for tuning, the first one is discarded
+pub const TUNE_CYCLES: u8 = 4;
+/// Distance from the setpoint in tenths of a degree before the relay flips
+const TUNE_HYSTERESIS: i16 = 5;
+/// Tuning is given up if the temperature doesn't oscillate within this time
+const TUNE_TIMEOUT_MS: u32 = 8 * 60 * 60 * 1000;
+
+/// Progress of the auto-tune
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TuneStep {
+    /// Still oscillating, with the output in percent
+    Running(u8),
+    /// The gains found
+    Done(Gains),
+    /// No usable oscillation before the timeout
+    Failed,
+}
+
+/// Relay auto-tune (Åström-Hägglund)
+/// The output is switched between its limits whenever the temperature crosses the setpoint.
+/// The amplitude and period of the resulting oscillation give the ultimate gain and period,
+/// from which the gains follow with the Ziegler-Nichols rules.
+pub struct AutoTune {
+    setpoint: i16,
+    cooling: bool,
+    limits: (u8, u8),
+    started: u32,
+    /// If the output is at its upper limit
+    high: bool,
+    /// Start of the current oscillation
+    cycle_start: Option<u32>,
+    /// Lowest and highest temperature of the current oscillation
+    extremes: (i16, i16),
+    cycles: u8,
+    /// Sum of the amplitudes (tenths of a degree) and periods (ms) of the counted oscillations
+    amplitudes: i32,
+    periods: u32,
+}
+
+impl AutoTune {
+    /// Starts tuning
+    /// param setpoint: Temperature to oscillate around in tenths of a degree
+    /// param cooling: If more output lowers the temperature
+    /// param limits: Min and Max output in percent
+    /// param now: Milliseconds since boot
+    pub fn new(setpoint: i16, cooling: bool, limits: (u8, u8), now: u32) -> Self {
+        AutoTune {
+            setpoint,
+            cooling,
+            limits,
+            started: now,
+            high: false,
+            cycle_start: None,
+            extremes: (i16::MAX, i16::MIN),
+            cycles: 0,
+            amplitudes: 0,
+            periods: 0,
+        }
+    }
+
+    /// returns the amount of full oscillations seen so far
+    pub fn cycles(&self) -> u8 {
+        self.cycles
+    }
+
+    /// Feeds a measurement to the relay
+    /// param measurement: Measured temperature in tenths of a degree
+    /// param now: Milliseconds since boot
+    /// returns the output to apply or the outcome
+    pub fn update(&mut self, measurement: i16, now: u32) -> TuneStep {
+        if now.wrapping_sub(self.started) > TUNE_TIMEOUT_MS {
+            return TuneStep::Failed;
+        }
+        self.extremes = (self.extremes.0.min(measurement), self.extremes.1.max(measurement));
+
+        let error = if self.cooling { measurement - self.setpoint } else { self.setpoint - measurement };
+        if !self.high && error > TUNE_HYSTERESIS {
+            // Every switch to high ends an oscillation
+            self.high = true;
+            if let Some(start) = self.cycle_start {
+                self.cycles += 1;
+                if self.cycles > 1 {
+                    self.amplitudes += ((self.extremes.1 - self.extremes.0) / 2) as i32;
+                    self.periods += now.wrapping_sub(start);
+                }
+                if self.cycles == TUNE_CYCLES {
+                    return TuneStep::Done(self.gains());
+                }
+            }
+            self.cycle_start = Some(now);
+            self.extremes = (measurement, measurement);
+        } else if self.high && error < -TUNE_HYSTERESIS {
+            self.high = false;
+        }
+        TuneStep::Running(if self.high { self.limits.1 } else { self.limits.0 })
+    }
+
+    /// Calculates the Ziegler-Nichols gains from the counted oscillations
+    fn gains(&self) -> Gains {
+        let counted = (TUNE_CYCLES - 1) as u32;
+        let amplitude = (self.amplitudes as u32 / counted).max(1);
+        let period = (self.periods / counted).max(1);
+        // Half the output swing in hundredths of a percent
+        let swing = self.limits.1.saturating_sub(self.limits.0) as u32 * 50;
+        // Ultimate gain 4d / (pi * a), with pi as 355 / 113 and a in tenths
+        let ultimate = 40 * swing * 113 / (355 * amplitude);
+        let kp = ultimate * 6 / 10;
+        // Ti = Pu / 2 and Td = Pu / 8, in minutes
+        let ki = kp as u64 * 120_000 / period as u64;
+        let kd = kp as u64 * period as u64 / 480_000;
+        let clamp = |gain: u64| gain.min(MAX_GAIN as u64) as u16;
+        (clamp(kp as u64), clamp(ki), clamp(kd))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAINS: Gains = (1000, 0, 0);
+
+    #[test]
+    fn output_is_proportional_to_the_error() {
+        let mut pid = Pid::default();
+        // 2.5C too cold at 10% per degree
+        assert_eq!(pid.update(600, 575, false, 0, GAINS, (0, 100)), 25);
+        // Cooling acts the other way round
+        assert_eq!(Pid::default().update(800, 850, true, 0, GAINS, (0, 100)), 50);
+        assert_eq!(Pid::default().update(800, 850, false, 0, GAINS, (0, 100)), 0);
+    }
+
+    #[test]
+    fn pulses_are_kept_within_the_minimum_times() {
+        // 120s is 20% of the window
+        assert_eq!(pulse_duty(9, (120, 120)), 0);
+        assert_eq!(pulse_duty(10, (120, 120)), 20);
+        assert_eq!(pulse_duty(50, (120, 120)), 50);
+        assert_eq!(pulse_duty(85, (120, 120)), 80);
+        assert_eq!(pulse_duty(91, (120, 120)), 100);
+        assert_eq!(pulse_duty(1, (0, 0)), 1);
+    }
+
+    #[test]
+    fn output_stays_within_the_limits() {
+        assert_eq!(Pid::default().update(600, 400, false, 0, GAINS, (10, 80)), 80);
+        assert_eq!(Pid::default().update(600, 700, false, 0, GAINS, (10, 80)), 10);
+    }
+
+    #[test]
+    fn integral_does_not_wind_up_while_saturated() {
+        let mut pid = Pid::default();
+        let gains = (1000, 1000, 0);
+        // An hour far below the setpoint at full output
+        for second in 0..3600 {
+            assert_eq!(pid.update(600, 400, false, second * 1000, gains, (0, 100)), 100);
+        }
+        // Just above the setpoint, the output drops right away instead of unwinding for hours
+        assert!(pid.update(600, 610, false, 3_601_000, gains, (0, 100)) < 100);
+        assert_eq!(pid.update(600, 620, false, 3_602_000, (1000, 0, 0), (0, 100)), 0);
+    }
+
+    #[test]
+    fn integral_removes_the_remaining_error() {
+        let mut pid = Pid::default();
+        // 1C too cold for 10 minutes at 0.6% per degree and minute
+        let mut output = 0;
+        for second in 0..=600 {
+            output = pid.update(600, 590, false, second * 1000, (0, 60, 0), (0, 100));
+        }
+        assert_eq!(output, 6);
+    }
+
+    #[test]
+    fn derivative_damps_fast_changes() {
+        let mut pid = Pid::default();
+        pid.update(600, 500, false, 0, (1000, 0, 100), (0, 100));
+        // Warming by 1C within a second, 60C per minute
+        assert_eq!(pid.update(600, 510, false, 1000, (1000, 0, 100), (0, 100)), 30);
+    }
+
+    #[test]
+    fn time_proportioning_spreads_the_output_over_the_window() {
+        assert!(time_proportion(25, PWM_WINDOW_MS / 4 - 1));
+        assert!(!time_proportion(25, PWM_WINDOW_MS / 4));
+        assert!(!time_proportion(0, 0));
+        assert!(time_proportion(100, PWM_WINDOW_MS - 1));
+    }
+
+    /// Tunes against a triangle wave of +-1C around the setpoint
+    /// param period: Period of the wave in milliseconds
+    /// returns the outcome of the auto-tune
+    fn tune_triangle(period: u32) -> TuneStep {
+        let mut tune = AutoTune::new(700, false, (0, 100), 0);
+        let mut step = TuneStep::Running(0);
+        for second in 0..(6 * period / 1000) {
+            let now = second * 1000;
+            let phase = (now % period) as i32 * 40 / period as i32;
+            // Start at the top, so the relay switches to high after the first fall
+            let measurement = 710 - if phase < 20 { phase } else { 40 - phase } as i16;
+            step = tune.update(measurement, now);
+            if !matches!(step, TuneStep::Running(_)) {
+                break;
+            }
+        }
+        step
+    }
+
+    #[test]
+    fn auto_tune_finds_gains_from_the_oscillation() {
+        // Ku = 4 * 50% / (pi * 1C) = 63.66% per degree, Kp = 0.6 Ku, Ki = Kp / 10min, Kd = Kp * 2.5min
+        assert_eq!(tune_triangle(20 * 60 * 1000), TuneStep::Done((3819, 381, 9547)));
+    }
+
+    #[test]
+    fn tuned_gains_can_still_be_edited() {
+        // A period of an hour gives Kd = Kp * 7.5min, beyond what the gains screen can show
+        assert_eq!(tune_triangle(60 * 60 * 1000), TuneStep::Done((3819, 127, MAX_GAIN)));
+    }
+
+    #[test]
+    fn auto_tune_gives_up_without_oscillation() {
+        let mut tune = AutoTune::new(700, false, (0, 100), 0);
+        assert_eq!(tune.update(600, 0), TuneStep::Running(100));
+        assert_eq!(tune.update(600, TUNE_TIMEOUT_MS + 1), TuneStep::Failed);
+    }
+}
@@ -0,0 +1,318 @@
+use crate::arbiter::Output;
+use crate::air::IAQ_DEADBAND;
+use crate::climate::{TemperatureUnit, Tenths};
+use crate::datetime::{write_two_digits, DateTime, DAY_NAMES};
+use crate::pid::{Gains, PidMode};
+use crate::schedule::Schedules;
+use heapless::String;
+use ufmt::uwrite;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Preferences {
+    pub temperature: (Tenths, Tenths), // Heat below, Vent above (Tenths of C)
+    pub humidity: (u8, u8),
+    pub date: DateTime,
+    pub watering: Schedules,
+    pub deadband: (Tenths, u8), // Temperature (Tenths of C), Humidity (%)
+    pub vent_timing: (u16, u16), // Min on, Min off (Seconds)
+    pub sprinkler_timing: (u16, u16), // Min on, Min off (Seconds)
+    pub fan_temperature: Tenths, // Exhaust fan above (Tenths of C)
+    pub heater_timing: (u16, u16), // Min on, Min off (Seconds)
+    pub fan_timing: (u16, u16), // Min on, Min off (Seconds)
+    pub proportional_vent: bool, // Position the vent servo by temperature instead of fully opening it
+    pub vent_travel: (u8, u8), // Closed, Open (Percent of the servo range)
+    pub vent_band: Tenths, // Fully open this far above the upper threshold (Tenths of C)
+    pub pid_mode: PidMode,
+    pub pid_gains: Gains, // Kp, Ki, Kd (Hundredths, see pid)
+    pub pid_limits: (u8, u8), // Min, Max output (Percent)
+    pub unit: TemperatureUnit, // Temperatures are shown and edited in this unit
+    pub iaq_vent: u16, // Vent and run the exhaust fan above this air quality index (0 = Off)
+    pub iaq_alert: u16, // Beep above this air quality index (0 = Off)
+}
+
+/// Checks if a value is above a threshold, with hysteresis
+/// An active output stays active until the value drops below the threshold by the deadband
+/// param threshold: The value above which the output switches on
+/// param deadband: Distance from the threshold needed to switch off
+/// param value: The measured value
+/// param active: If the output is currently active
+/// returns if the output should be active
+fn above(threshold: Tenths, deadband: Tenths, value: Tenths, active: bool) -> bool {
+    if active { value > threshold.saturating_sub(deadband) } else { value > threshold }
+}
+
+/// Checks if a value is below a threshold, with hysteresis
+/// An active output stays active until the value rises above the threshold by the deadband
+/// param threshold: The value below which the output switches on
+/// param deadband: Distance from the threshold needed to switch off
+/// param value: The measured value
+/// param active: If the output is currently active
+/// returns if the output should be active
+fn below(threshold: Tenths, deadband: Tenths, value: Tenths, active: bool) -> bool {
+    if active { value < threshold.saturating_add(deadband) } else { value < threshold }
+}
+
+/// Checks a value against a range with hysteresis
+/// An active output stays active until the value is back inside the range by the deadband
+/// param range: Lower and upper bound (inclusive)
+/// param deadband: Distance from the bounds needed to switch off
+/// param value: The measured value
+/// param active: If the output is currently active
+/// returns if the output should be active
+fn out_of_range(range: (u16, u16), deadband: u16, value: u16, active: bool) -> bool {
+    if !active {
+        return value < range.0 || value > range.1;
+    }
+    // Keep the band from turning inside out on narrow ranges
+    let deadband = deadband.min(range.1.saturating_sub(range.0) / 2);
+    value < range.0.saturating_add(deadband) || value > range.1 - deadband
+}
+
+/// Checks an air quality index against a trigger level, with hysteresis
+/// param level: The index above which the trigger fires, 0 if it is off
+/// param iaq: Air quality index, None while it is unknown
+/// param active: If the trigger is currently active
+/// returns if the trigger should be active
+fn air_trigger(level: u16, iaq: Option<u16>, active: bool) -> bool {
+    let Some(iaq) = iaq.filter(|_| level > 0) else {
+        return false;
+    };
+    if active { iaq > level.saturating_sub(IAQ_DEADBAND) } else { iaq > level }
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            temperature: (156, 267), // Ideal range is 60F - 80F
+            humidity: (60, 70), // Ideal range is 60% - 70%
+            date: DateTime::default(), // Date: 00:00:00 Jan 1 2000
+            watering: Schedules::new(), // No default watering times set
+            deadband: (11, 3), // Switch off 2F/3% inside the range
+            vent_timing: (60, 60), // Don't cycle the vent motor more than once a minute
+            sprinkler_timing: (30, 120), // Mist for at least 30s, then let the valve rest for 2min
+            fan_temperature: 294, // 85F, second stage once the open vent can't keep up
+            heater_timing: (120, 120), // Let the heater warm up and cool down
+            fan_timing: (60, 60), // Don't cycle the fan motor more than once a minute
+            proportional_vent: false, // Plain open/close vent
+            vent_travel: (0, 100), // Full servo range
+            vent_band: 33, // Fully open at 86F
+            pid_mode: PidMode::Off, // On/off control until configured
+            pid_gains: (2000, 200, 0), // 20% per C, 2% per C and minute
+            pid_limits: (0, 100), // Full output range
+            unit: TemperatureUnit::Fahrenheit,
+            iaq_vent: 0, // Air quality is only shown until configured
+            iaq_alert: 0,
+        }
+    }
+}
+
+impl Preferences {
+    /// Gets the date in the HH:MM:SS Www DD/MM/YYYY format
+    /// returns: (HH:MM:SS, Www DD/MM/YYYY)
+    pub fn get_date_formatted(&self) -> (String<8>, String<14>) {
+        // Format the date as a string
+        let mut val1: String<8> = String::new();
+        let mut val2: String<14> = String::new();
+        let date = &self.date;
+        write_two_digits(&mut val1, date.hour());
+        uwrite!(&mut val1, ":").unwrap();
+        write_two_digits(&mut val1, date.minute());
+        uwrite!(&mut val1, ":").unwrap();
+        write_two_digits(&mut val1, date.second());
+        uwrite!(&mut val2, "{} ", DAY_NAMES[date.weekday() as usize]).unwrap();
+        write_two_digits(&mut val2, date.day());
+        uwrite!(&mut val2, "/").unwrap();
+        write_two_digits(&mut val2, date.month());
+        uwrite!(&mut val2, "/{}", date.year()).unwrap();
+        (val1, val2)
+    }
+
+    /// Gets the humidity range in tenths of a percent, to compare with readings
+    fn humidity_tenths(&self) -> (u16, u16) {
+        (self.humidity.0 as u16 * 10, self.humidity.1 as u16 * 10)
+    }
+
+    /// Gets the temperature deadband, limited so heating and venting never overlap
+    fn temperature_deadband(&self) -> Tenths {
+        self.deadband.0.min(self.temperature.1.saturating_sub(self.temperature.0) / 2)
+    }
+
+    /// Checks if the roof vent should be open because it is too hot, with hysteresis
+    /// param temperature: Temperature in tenths of a degree Celsius
+    /// param open: If the roof vent is currently open
+    /// returns if the roof vent should be open
+    pub fn needs_venting(&self, temperature: Tenths, open: bool) -> bool {
+        above(self.temperature.1, self.temperature_deadband(), temperature, open)
+    }
+
+    /// Checks if the heater should run because it is too cold, with hysteresis
+    /// param temperature: Temperature in tenths of a degree Celsius
+    /// param on: If the heater is currently running
+    /// returns if the heater should run
+    pub fn needs_heating(&self, temperature: Tenths, on: bool) -> bool {
+        below(self.temperature.0, self.temperature_deadband(), temperature, on)
+    }
+
+    /// Checks if the exhaust fan should run in addition to the open vent, with hysteresis
+    /// param temperature: Temperature in tenths of a degree Celsius
+    /// param on: If the exhaust fan is currently running
+    /// returns if the exhaust fan should run
+    pub fn needs_exhaust(&self, temperature: Tenths, on: bool) -> bool {
+        above(self.fan_temperature, self.deadband.0, temperature, on)
+    }
+
+    /// Gets the minimum run and rest times of an actuator
+    /// param output: The actuator
+    /// returns the Min on, Min off time in seconds
+    pub fn timing(&self, output: Output) -> (u16, u16) {
+        match output {
+            Output::RoofVent => self.vent_timing,
+            Output::Sprinklers => self.sprinkler_timing,
+            Output::Heater => self.heater_timing,
+            Output::ExhaustFan => self.fan_timing,
+        }
+    }
+
+    /// Gets the minimum run and rest times of an actuator for editing
+    /// param output: The actuator
+    /// returns the Min on, Min off time in seconds
+    pub fn timing_mut(&mut self, output: Output) -> &mut (u16, u16) {
+        match output {
+            Output::RoofVent => &mut self.vent_timing,
+            Output::Sprinklers => &mut self.sprinkler_timing,
+            Output::Heater => &mut self.heater_timing,
+            Output::ExhaustFan => &mut self.fan_timing,
+        }
+    }
+
+    /// Checks if the sprinklers should be enabled, with hysteresis
+    /// param humidity: Humidity in tenths of a percent
+    /// param on: If the sprinklers are currently enabled
+    /// returns if the sprinklers should be enabled
+    pub fn needs_sprinkling(&self, humidity: u16, on: bool) -> bool {
+        out_of_range(self.humidity_tenths(), self.deadband.1 as u16 * 10, humidity, on)
+    }
+
+    /// Gets the humidity the greenhouse is kept at, the middle of the range
+    /// returns the humidity in tenths of a percent
+    pub fn humidity_target(&self) -> u16 {
+        (self.humidity.0 as u16 + self.humidity.1 as u16) * 5
+    }
+
+    /// Checks if the air has to be exchanged because of its quality, with hysteresis
+    /// param iaq: Air quality index, None while it is unknown
+    /// param on: If the air is currently being exchanged
+    /// returns if the roof vent and exhaust fan should run
+    pub fn needs_air_exchange(&self, iaq: Option<u16>, on: bool) -> bool {
+        air_trigger(self.iaq_vent, iaq, on)
+    }
+
+    /// Checks if the air quality alert should sound, with hysteresis
+    /// param iaq: Air quality index, None while it is unknown
+    /// param on: If the alert is currently sounding
+    /// returns if the alert should sound
+    pub fn needs_air_alert(&self, iaq: Option<u16>, on: bool) -> bool {
+        air_trigger(self.iaq_alert, iaq, on)
+    }
+
+    /// Checks if it is time to enable the sprinklers
+    /// returns if the current time is within any of the watering schedules
+    /// returns false if there is no watering schedule set
+    pub fn is_watering_time(&self) -> bool {
+        let (day, now) = (self.date.weekday(), self.date.minute_of_day());
+        self.watering.iter().any(|watering| watering.is_due(day, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::{Watering, EVERY_DAY};
+    use core::time::Duration;
+
+    /// returns Preferences set to the given date and time
+    fn at(year: u16, month: u8, day: u8, hour: u8, minute: u8) -> Preferences {
+        Preferences { date: DateTime::new(year, month, day, hour, minute, 0).unwrap(), ..Preferences::default() }
+    }
+
+    #[test]
+    fn deadband_keeps_outputs_on_until_well_inside_the_range() {
+        let preferences = Preferences::default();
+        assert!(preferences.needs_venting(268, false));
+        assert!(preferences.needs_venting(257, true));
+        assert!(!preferences.needs_venting(256, true));
+        assert!(!preferences.needs_venting(266, false));
+        assert!(preferences.needs_heating(155, false));
+        assert!(preferences.needs_heating(166, true));
+        assert!(!preferences.needs_heating(167, true));
+        assert!(preferences.needs_exhaust(284, true));
+        assert!(!preferences.needs_exhaust(284, false));
+        assert!(preferences.needs_sprinkling(580, true));
+        assert!(preferences.needs_sprinkling(629, true));
+        assert!(!preferences.needs_sprinkling(630, true));
+    }
+
+    #[test]
+    fn deadband_is_limited_on_narrow_ranges() {
+        let preferences = Preferences { temperature: (211, 222), deadband: (28, 3), ..Preferences::default() };
+        assert!(!preferences.needs_venting(216, true));
+        assert!(preferences.needs_venting(218, true));
+        assert!(!preferences.needs_heating(216, true));
+    }
+
+    #[test]
+    fn frost_is_below_the_range() {
+        let preferences = Preferences { temperature: (20, 100), ..Preferences::default() };
+        assert!(preferences.needs_heating(-50, false));
+        assert!(!preferences.needs_venting(-50, false));
+    }
+
+    #[test]
+    fn air_quality_triggers_are_off_until_configured() {
+        let mut preferences = Preferences::default();
+        assert!(!preferences.needs_air_exchange(Some(400), false));
+        preferences.iaq_vent = 150;
+        assert!(!preferences.needs_air_exchange(None, true));
+        assert!(preferences.needs_air_exchange(Some(151), false));
+        assert!(preferences.needs_air_exchange(Some(126), true));
+        assert!(!preferences.needs_air_exchange(Some(125), true));
+    }
+
+    #[test]
+    fn watering_time_is_off_without_a_schedule() {
+        assert!(!Preferences::default().is_watering_time());
+    }
+
+    #[test]
+    fn formatted_date_starts_with_the_weekday() {
+        let (time, date) = at(2099, 8, 4, 7, 5).get_date_formatted();
+        assert_eq!(time.as_str(), "07:05:00");
+        assert_eq!(date.as_str(), "Tue 04/08/2099");
+    }
+
+    #[test]
+    fn any_schedule_starts_the_watering() {
+        let mut preferences = at(2024, 7, 15, 18, 5);
+        // Morning every day, evening on Monday, Wednesday and Friday; the 15th is a Monday
+        preferences.watering.push(Watering { start: (6, 0), duration: 10, days: EVERY_DAY }).unwrap();
+        preferences.watering.push(Watering { start: (18, 0), duration: 15, days: 0b001_0101 }).unwrap();
+        assert!(preferences.is_watering_time());
+        preferences.date = preferences.date + Duration::from_secs(24 * 60 * 60);
+        assert!(!preferences.is_watering_time());
+        preferences.date = preferences.date - Duration::from_secs(12 * 60 * 60);
+        assert!(preferences.is_watering_time());
+    }
+
+    #[test]
+    fn watering_continues_into_the_next_month() {
+        // Sunday 31 March 2024, 23:50 - 00:10
+        let mut preferences = at(2024, 3, 31, 23, 55);
+        preferences.watering.push(Watering { start: (23, 50), duration: 20, days: 1 << 6 }).unwrap();
+        assert!(preferences.is_watering_time());
+        preferences.date = preferences.date + Duration::from_secs(10 * 60);
+        assert_eq!(preferences.date, DateTime::new(2024, 4, 1, 0, 5, 0).unwrap());
+        assert!(preferences.is_watering_time());
+        preferences.date = preferences.date + Duration::from_secs(5 * 60);
+        assert!(!preferences.is_watering_time());
+    }
+}
@@ -0,0 +1,135 @@
+//! Minimum run and rest times for actuators
+//!
+//! Climate control asks for a state on every poll, but motors and valves wear out when
+//! they are cycled that often. A Relay only switches once the actuator has spent its
+//! minimum time in the current state; until then the request is held back.
+
+use crate::hal::Actuator;
+
+/// An actuator that is switched no more often than its minimum run and rest times allow
+pub struct Relay<A> {
+    actuator: A,
+    /// The state climate control asked for last
+    requested: bool,
+    /// When the actuator was last switched, None if it never was
+    changed_at: Option<u32>,
+}
+
+impl<A: Actuator> Relay<A> {
+    /// Wraps an actuator
+    /// The first switch is allowed right away
+    /// param actuator: The actuator to guard
+    pub fn new(actuator: A) -> Self {
+        let requested = actuator.is_on();
+        Relay { actuator, requested, changed_at: None }
+    }
+
+    /// Asks for a state, which is applied by the next call to apply()
+    /// Only the last request before apply() counts
+    /// param on: The requested state
+    pub fn request(&mut self, on: bool) {
+        self.requested = on;
+    }
+
+    /// Switches to the requested state if the actuator has been in its current state long enough
+    /// param now: Milliseconds since boot
+    /// param timing: Minimum run and rest time in seconds
+    pub fn apply(&mut self, now: u32, timing: (u16, u16)) {
+        let on = self.actuator.is_on();
+        if self.requested == on {
+            return;
+        }
+        let minimum = if on { timing.0 } else { timing.1 } as u32 * 1000;
+        if let Some(changed_at) = self.changed_at {
+            if now.wrapping_sub(changed_at) < minimum {
+                return;
+            }
+        }
+        self.switch(self.requested, now);
+    }
+
+    /// Switches immediately, ignoring the minimum times
+    /// Used when safety matters more than wear, e.g. when smoke is detected
+    /// param on: The new state
+    /// param now: Milliseconds since boot
+    pub fn force(&mut self, on: bool, now: u32) {
+        self.requested = on;
+        if self.actuator.is_on() != on {
+            self.switch(on, now);
+        }
+    }
+
+    /// returns if the actuator is on
+    pub fn is_on(&self) -> bool {
+        self.actuator.is_on()
+    }
+
+    /// Switches the actuator and remembers when
+    /// param on: The new state
+    /// param now: Milliseconds since boot
+    fn switch(&mut self, on: bool, now: u32) {
+        self.actuator.set_on(on);
+        self.changed_at = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockActuator(bool);
+
+    impl Actuator for MockActuator {
+        fn set_on(&mut self, on: bool) {
+            self.0 = on;
+        }
+
+        fn is_on(&self) -> bool {
+            self.0
+        }
+    }
+
+    const TIMING: (u16, u16) = (30, 120);
+
+    #[test]
+    fn first_switch_is_immediate() {
+        let mut relay = Relay::new(MockActuator(false));
+        relay.request(true);
+        relay.apply(0, TIMING);
+        assert!(relay.is_on());
+    }
+
+    #[test]
+    fn minimum_run_and_rest_times_are_kept() {
+        let mut relay = Relay::new(MockActuator(false));
+        relay.request(true);
+        relay.apply(1000, TIMING);
+
+        relay.request(false);
+        relay.apply(30_999, TIMING);
+        assert!(relay.is_on());
+        relay.apply(31_000, TIMING);
+        assert!(!relay.is_on());
+
+        relay.request(true);
+        relay.apply(150_999, TIMING);
+        assert!(!relay.is_on());
+        relay.apply(151_000, TIMING);
+        assert!(relay.is_on());
+    }
+
+    #[test]
+    fn forced_switches_ignore_the_minimum_times() {
+        let mut relay = Relay::new(MockActuator(false));
+        relay.request(true);
+        relay.apply(0, TIMING);
+        relay.force(false, 10);
+        assert!(!relay.is_on());
+        // The rest time counts from the forced switch
+        relay.request(true);
+        relay.apply(120_009, TIMING);
+        assert!(!relay.is_on());
+        relay.apply(u32::MAX, TIMING);
+        assert!(relay.is_on());
+    }
+}
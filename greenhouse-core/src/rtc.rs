@@ -0,0 +1,163 @@
+//! Driver for the DS3231 real-time clock
+//!
+//! The DS1307 uses the same address and time registers, so it is supported as well.
+
+use crate::hal::RealTimeClock;
+use crate::datetime::DateTime;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+const ADDRESS: u8 = 0x68;
+/// Register holding the seconds; the other time registers follow it
+const TIME_REGISTER: u8 = 0x00;
+/// Seconds register: the oscillator of a DS1307 is stopped
+const CLOCK_HALT: u8 = 0x80;
+/// Hours register: 12 hour mode is used
+const TWELVE_HOUR: u8 = 0x40;
+/// Hours register in 12 hour mode: it is past noon
+const PM: u8 = 0x20;
+
+#[derive(Debug)]
+pub enum Error<E> {
+    /// The I2C transfer failed, usually because no RTC is connected
+    Bus(E),
+    /// The RTC is stopped or holds no valid date (e.g. after its battery died)
+    InvalidDate,
+}
+
+pub struct Ds3231<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C> Ds3231<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        Ds3231 { i2c }
+    }
+}
+
+fn bcd_to_bin(bcd: u8) -> u8 {
+    (bcd >> 4) * 10 + (bcd & 0x0F)
+}
+
+fn bin_to_bcd(bin: u8) -> u8 {
+    ((bin / 10) << 4) | (bin % 10)
+}
+
+impl<I2C, E> RealTimeClock for Ds3231<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    type Error = Error<E>;
+
+    fn get_date(&mut self) -> Result<DateTime, Error<E>> {
+        let mut registers = [0u8; 7];
+        self.i2c.write_read(ADDRESS, &[TIME_REGISTER], &mut registers).map_err(Error::Bus)?;
+        if registers[0] & CLOCK_HALT != 0 {
+            return Err(Error::InvalidDate);
+        }
+
+        let sec = bcd_to_bin(registers[0]);
+        let min = bcd_to_bin(registers[1]);
+        let hour = if registers[2] & TWELVE_HOUR != 0 {
+            bcd_to_bin(registers[2] & 0x1F) % 12 + if registers[2] & PM != 0 { 12 } else { 0 }
+        } else {
+            bcd_to_bin(registers[2] & 0x3F)
+        };
+        // registers[3] is the day of the week
+        let day = bcd_to_bin(registers[4] & 0x3F);
+        let month = bcd_to_bin(registers[5] & 0x1F); // Bit 7 is the DS3231 century flag
+        let year = 2000 + bcd_to_bin(registers[6]) as u16;
+        DateTime::new(year, month, day, hour, min, sec).ok_or(Error::InvalidDate)
+    }
+
+    fn set_date(&mut self, date: DateTime) -> Result<(), Error<E>> {
+        let (sec, min, hour, day, month, year) = (date.second(), date.minute(), date.hour(), date.day(), date.month(), date.year());
+        // Writing the seconds also clears the clock halt flag of the DS1307
+        self.i2c
+            .write(ADDRESS, &[
+                TIME_REGISTER,
+                bin_to_bcd(sec),
+                bin_to_bcd(min),
+                bin_to_bcd(hour), // 24 hour mode
+                1, // The day of the week is not used
+                bin_to_bcd(day),
+                bin_to_bcd(month),
+                bin_to_bcd((year - 2000) as u8),
+            ])
+            .map_err(Error::Bus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Register file of an RTC
+    struct MockBus {
+        registers: [u8; 7],
+        connected: bool,
+    }
+
+    impl Write for MockBus {
+        type Error = ();
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+            if !self.connected || address != ADDRESS {
+                return Err(());
+            }
+            let start = bytes[0] as usize;
+            self.registers[start..start + bytes.len() - 1].copy_from_slice(&bytes[1..]);
+            Ok(())
+        }
+    }
+
+    impl WriteRead for MockBus {
+        type Error = ();
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+            if !self.connected || address != ADDRESS {
+                return Err(());
+            }
+            let start = bytes[0] as usize;
+            buffer.copy_from_slice(&self.registers[start..start + buffer.len()]);
+            Ok(())
+        }
+    }
+
+    fn rtc(registers: [u8; 7]) -> Ds3231<MockBus> {
+        Ds3231::new(MockBus { registers, connected: true })
+    }
+
+    #[test]
+    fn reads_bcd_date() {
+        let mut rtc = rtc([0x45, 0x30, 0x21, 0x03, 0x29, 0x02, 0x24]);
+        assert_eq!(rtc.get_date().unwrap(), DateTime::new(2024, 2, 29, 21, 30, 45).unwrap());
+    }
+
+    #[test]
+    fn converts_twelve_hour_mode() {
+        assert_eq!(rtc([0x00, 0x00, TWELVE_HOUR | PM | 0x12, 0x01, 0x01, 0x01, 0x24]).get_date().unwrap().hour(), 12);
+        assert_eq!(rtc([0x00, 0x00, TWELVE_HOUR | 0x12, 0x01, 0x01, 0x01, 0x24]).get_date().unwrap().hour(), 0);
+        assert_eq!(rtc([0x00, 0x00, TWELVE_HOUR | PM | 0x07, 0x01, 0x01, 0x01, 0x24]).get_date().unwrap().hour(), 19);
+    }
+
+    #[test]
+    fn written_date_is_read_back() {
+        let mut rtc = rtc([CLOCK_HALT, 0, 0, 0, 0, 0, 0]);
+        let date = DateTime::new(2099, 12, 31, 9, 8, 7).unwrap();
+        rtc.set_date(date).unwrap();
+        assert_eq!(rtc.get_date().unwrap(), date);
+    }
+
+    #[test]
+    fn rejects_stopped_or_invalid_clocks() {
+        assert!(matches!(rtc([CLOCK_HALT, 0, 0, 1, 1, 1, 0]).get_date(), Err(Error::InvalidDate)));
+        assert!(matches!(rtc([0, 0, 0, 1, 0x30, 0x02, 0x23]).get_date(), Err(Error::InvalidDate)));
+        assert!(matches!(rtc([0, 0, 0, 1, 0x01, 0x13, 0x23]).get_date(), Err(Error::InvalidDate)));
+    }
+
+    #[test]
+    fn missing_rtc_reports_bus_error() {
+        let mut rtc = Ds3231::new(MockBus { registers: [0; 7], connected: false });
+        assert!(matches!(rtc.get_date(), Err(Error::Bus(()))));
+    }
+}
@@ -0,0 +1,185 @@
+//! Watering schedules
+//!
+//! Every schedule waters for a number of minutes from a start time, on the weekdays
+//! selected in its mask. Up to MAX_SCHEDULES are kept, so beds can be watered several
+//! times a day or only on some days of the week.
+//!
+//! Times are compared as minutes since midnight. A window may continue past midnight,
+//! the part after midnight belongs to the day it started on: a Monday schedule from
+//! 22:00 to 02:00 also runs early on Tuesday, but not early on Monday.
+
+use crate::datetime::write_two_digits;
+use heapless::{String, Vec};
+use ufmt::uwrite;
+
+/// Schedules that fit into the Preferences
+pub const MAX_SCHEDULES: usize = 4;
+/// Longest watering in minutes
+pub const MAX_DURATION: u16 = 240;
+/// Minutes in a day
+pub const DAY_MINUTES: u16 = 24 * 60;
+/// Mask of every day of the week
+pub const EVERY_DAY: u8 = 0b111_1111;
+/// First letter of every weekday, starting with Monday
+const DAY_LETTERS: [char; 7] = ['M', 'T', 'W', 'T', 'F', 'S', 'S'];
+
+/// The watering schedules
+pub type Schedules = Vec<Watering, MAX_SCHEDULES>;
+
+/// A single watering schedule
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Watering {
+    /// Hour, Minute
+    pub start: (u8, u8),
+    /// Minutes the sprinklers run
+    pub duration: u16,
+    /// Days the schedule runs on, bit 0 is Monday
+    pub days: u8,
+}
+
+impl Default for Watering {
+    fn default() -> Self {
+        // 10 minutes in the morning, every day
+        Watering { start: (6, 0), duration: 10, days: EVERY_DAY }
+    }
+}
+
+impl Watering {
+    /// Checks if the sprinklers should run
+    /// param weekday: Day of the week, 0 is Monday
+    /// param now: Minutes since midnight
+    /// returns if the time lies within the window started today or the one started yesterday
+    pub fn is_due(&self, weekday: u8, now: u16) -> bool {
+        let start = self.start_minute();
+        let end = start + self.duration;
+        let yesterday = (weekday + 6) % 7;
+        (self.runs_on(weekday) && now >= start && now < end) || (self.runs_on(yesterday) && now + DAY_MINUTES < end)
+    }
+
+    /// returns the start time in minutes since midnight
+    pub fn start_minute(&self) -> u16 {
+        self.start.0 as u16 * 60 + self.start.1 as u16
+    }
+
+    /// param weekday: Day of the week, 0 is Monday
+    /// returns if the schedule runs on that day
+    pub fn runs_on(&self, weekday: u8) -> bool {
+        self.days & (1 << weekday) != 0
+    }
+
+    /// Selects or deselects a day
+    /// param weekday: Day of the week, 0 is Monday
+    /// param on: If the schedule should run on that day
+    pub fn set_day(&mut self, weekday: u8, on: bool) {
+        if on {
+            self.days |= 1 << weekday;
+        } else {
+            self.days &= !(1 << weekday);
+        }
+    }
+
+    /// Formats the start and duration: HH:MM NNNmin
+    pub fn format_time(&self) -> String<12> {
+        let mut str: String<12> = String::new();
+        write_two_digits(&mut str, self.start.0);
+        uwrite!(str, ":").unwrap();
+        write_two_digits(&mut str, self.start.1);
+        uwrite!(str, " {}min", self.duration).unwrap(); // Max str size 12
+        str
+    }
+
+    /// Formats the days, with a dash for the days it doesn't run: M-W-F--
+    pub fn format_days(&self) -> String<7> {
+        let mut str: String<7> = String::new();
+        for (weekday, letter) in DAY_LETTERS.iter().enumerate() {
+            str.push(if self.runs_on(weekday as u8) { *letter } else { '-' }).unwrap();
+        }
+        str
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// param hour: Hour of the day
+    /// param minute: Minute of the hour
+    /// returns the minutes since midnight
+    fn at(hour: u16, minute: u16) -> u16 {
+        hour * 60 + minute
+    }
+
+    #[test]
+    fn schedules_only_run_on_their_days() {
+        // 18:00 - 18:15 on Monday, Wednesday and Friday
+        let watering = Watering { start: (18, 0), duration: 15, days: 0b001_0101 };
+        assert!(watering.is_due(0, at(18, 0)));
+        assert!(watering.is_due(4, at(18, 14)));
+        assert!(!watering.is_due(1, at(18, 5)));
+        assert!(!watering.is_due(2, at(17, 59)));
+        assert_eq!(watering.format_days().as_str(), "M-W-F--");
+        assert_eq!(watering.format_time().as_str(), "18:00 15min");
+    }
+
+    #[test]
+    fn windows_start_inclusive_and_end_exclusive() {
+        let watering = Watering { start: (6, 0), duration: 10, days: EVERY_DAY };
+        assert!(!watering.is_due(0, at(5, 59)));
+        assert!(watering.is_due(0, at(6, 0)));
+        assert!(watering.is_due(0, at(6, 9)));
+        assert!(!watering.is_due(0, at(6, 10)));
+        assert_eq!(watering.format_time().as_str(), "06:00 10min");
+    }
+
+    #[test]
+    fn windows_span_full_hours() {
+        // 06:50 - 07:10, the minutes of the end lie below those of the start
+        let watering = Watering { start: (6, 50), duration: 20, days: EVERY_DAY };
+        assert!(watering.is_due(3, at(6, 55)));
+        assert!(watering.is_due(3, at(7, 5)));
+        assert!(!watering.is_due(3, at(7, 10)));
+        assert!(!watering.is_due(3, at(6, 5)));
+        assert!(!watering.is_due(3, at(7, 55)));
+    }
+
+    #[test]
+    fn windows_continue_past_midnight() {
+        // 22:00 - 02:00 every day
+        let watering = Watering { start: (22, 0), duration: 240, days: EVERY_DAY };
+        assert!(!watering.is_due(0, at(21, 59)));
+        assert!(watering.is_due(0, at(22, 0)));
+        assert!(watering.is_due(0, at(23, 59)));
+        assert!(watering.is_due(1, at(0, 0)));
+        assert!(watering.is_due(1, at(1, 59)));
+        assert!(!watering.is_due(1, at(2, 0)));
+        assert!(!watering.is_due(1, at(12, 0)));
+    }
+
+    #[test]
+    fn the_part_after_midnight_belongs_to_the_start_day() {
+        // 23:30 - 00:30, only started on Sunday
+        let watering = Watering { start: (23, 30), duration: 60, days: 1 << 6 };
+        assert!(watering.is_due(6, at(23, 45)));
+        assert!(watering.is_due(0, at(0, 15)));
+        assert!(!watering.is_due(6, at(0, 15)));
+        assert!(!watering.is_due(0, at(23, 45)));
+    }
+
+    #[test]
+    fn windows_ending_at_midnight_stop_there() {
+        let watering = Watering { start: (23, 59), duration: 1, days: EVERY_DAY };
+        assert!(watering.is_due(2, at(23, 59)));
+        assert!(!watering.is_due(3, at(0, 0)));
+        assert!(!watering.is_due(2, at(0, 0)));
+    }
+
+    #[test]
+    fn schedules_without_days_never_run() {
+        let mut watering = Watering { start: (0, 0), duration: MAX_DURATION, days: EVERY_DAY };
+        for weekday in 0..7 {
+            watering.set_day(weekday, false);
+        }
+        assert_eq!(watering.days, 0);
+        assert!((0..7).all(|weekday| !watering.is_due(weekday, at(1, 0))));
+    }
+}
@@ -0,0 +1,1370 @@
+//! The screens of the menu
+//!
+//! New screens implement `menu::Screen` and are added to SCREENS, which defines
+//! the order they are browsed in with UP and DOWN.
+
+use crate::air;
+use crate::arbiter::{Decision, Output, Outputs};
+use crate::climate::{Reading, TemperatureUnit, Tenths};
+use crate::controller::render_screen;
+use crate::datetime::{days_in_month, DateTime, DAY_NAMES, FIRST_YEAR, LAST_YEAR};
+use crate::field::Field;
+use crate::hal::Display;
+use crate::menu::{render_edit_screen, render_field_edit_screen, Screen, Session};
+use crate::pid::{PidMode, MAX_GAIN, TUNE_CYCLES};
+use crate::schedule::{Watering, MAX_DURATION, MAX_SCHEDULES};
+use crate::Preferences;
+use core::time::Duration;
+use heapless::String;
+use ufmt::uwrite;
+
+/// Every screen in the order they are browsed in
+pub static SCREENS: [&dyn Screen; 8] = [
+    &TemperatureScreen,
+    &HumidityScreen,
+    &PressureScreen,
+    &GasScreen,
+    &DateScreen,
+    &WateringScreen,
+    &OutputsScreen([Output::RoofVent, Output::Sprinklers]),
+    &OutputsScreen([Output::Heater, Output::ExhaustFan]),
+];
+
+/// The hidden service menu, opened and closed by pressing UP and DOWN together
+pub static SERVICE_SCREENS: [&dyn Screen; 12] = [
+    &DiagnosticsScreen,
+    &UnitScreen,
+    &FanScreen,
+    &VentScreen,
+    &VentTravelScreen,
+    &PidScreen,
+    &PidGainsScreen,
+    &DeadbandScreen,
+    &TimingScreen(Output::RoofVent),
+    &TimingScreen(Output::Sprinklers),
+    &TimingScreen(Output::Heater),
+    &TimingScreen(Output::ExhaustFan),
+];
+
+/// Range of the temperature thresholds in Fahrenheit
+pub const TEMPERATURE_F: Field = Field::new(-22, 122);
+/// Range of the temperature thresholds in Celsius
+pub const TEMPERATURE_C: Field = Field::new(-30, 50);
+/// Range of the humidity thresholds in percent
+pub const HUMIDITY: Field = Field::new(0, 100);
+const MINUTE: Field = Field::new(0, 59).wrapping();
+const HOUR: Field = Field::new(0, 23).wrapping();
+const MONTH: Field = Field::new(1, 12).wrapping();
+/// The RTC stores the year in two digits
+const YEAR: Field = Field::new(FIRST_YEAR as i32, LAST_YEAR as i32);
+/// Minutes a watering schedule runs
+pub const WATERING_DURATION: Field = Field::new(1, MAX_DURATION as i32);
+/// Auto, On, Off
+const OVERRIDE: Field = Field::new(0, 2).wrapping();
+/// Air quality index above which a trigger fires, 0 is Off
+pub const IAQ_TRIGGER: Field = Field::new(0, air::IAQ_MAX as i32).with_step(25);
+/// Fahrenheit, Celsius
+const UNIT: Field = Field::new(0, 1).wrapping();
+/// On/off, Proportional
+const VENT_MODE: Field = Field::new(0, 1).wrapping();
+/// Degrees from closed to fully open
+pub const VENT_BAND: Field = Field::new(1, 30);
+/// Percent of the servo range
+pub const TRAVEL: Field = Field::new(0, 100);
+/// Distance from the thresholds needed to switch an actuator off again
+pub const DEADBAND: Field = Field::new(0, 10);
+/// Off, Heater, Vent
+const PID_MODE: Field = Field::new(0, 2).wrapping();
+/// PID output limits in percent
+pub const PID_LIMIT: Field = Field::new(0, 100);
+/// No, Yes
+const YES_NO: Field = Field::new(0, 1).wrapping();
+/// PID gains in hundredths
+pub const GAIN: Field = Field::new(0, MAX_GAIN as i32).with_step(10);
+/// Minimum run and rest times in seconds
+pub const DURATION: Field = Field::new(0, 900).with_step(5);
+
+/// Gets the range of a temperature threshold
+/// param unit: The unit the threshold is edited in
+pub fn temperature_field(unit: TemperatureUnit) -> Field {
+    match unit {
+        TemperatureUnit::Fahrenheit => TEMPERATURE_F,
+        TemperatureUnit::Celsius => TEMPERATURE_C,
+    }
+}
+
+/// Writes a temperature in whole degrees of the preferred unit, e.g. "-5C"
+/// param out: The string to append to
+/// param tenths: Temperature in tenths of a degree Celsius
+/// param unit: The unit to show
+fn write_temperature<const N: usize>(out: &mut String<N>, tenths: Tenths, unit: TemperatureUnit) {
+    uwrite!(out, "{}{}", unit.to_degrees(tenths), unit.symbol()).unwrap();
+}
+
+/// Writes a temperature difference in whole degrees of the preferred unit, e.g. "2F"
+/// param out: The string to append to
+/// param tenths: Difference in tenths of a degree Celsius
+/// param unit: The unit to show
+fn write_difference<const N: usize>(out: &mut String<N>, tenths: Tenths, unit: TemperatureUnit) {
+    uwrite!(out, "{}{}", unit.to_difference(tenths), unit.symbol()).unwrap();
+}
+
+/// Temperature and the range the heater and roof vent keep it in
+pub struct TemperatureScreen;
+
+impl Screen for TemperatureScreen {
+    fn render(&self, reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let unit = preferences.unit;
+        let mut data_str: String<12> = String::new();
+        uwrite!(&mut data_str, "Temp: ").unwrap();
+        match reading {
+            Some(reading) => {
+                write_tenths(&mut data_str, unit.to_tenths(reading.temperature) as i32);
+                uwrite!(&mut data_str, "{}", unit.symbol()).unwrap(); // Max str size 12
+            }
+            None => uwrite!(&mut data_str, "--").unwrap(), // Str size 8
+        }
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        let (low, high) = preferences.temperature;
+        uwrite!(&mut data_str, "({}, {})", unit.to_degrees(low), unit.to_degrees(high)).unwrap(); // Max str size 10
+        render_screen(&data_str, false, lcd);
+    }
+
+    fn fields(&self) -> u8 {
+        2
+    }
+
+    fn field(&self, _field: u8, preferences: &Preferences, _session: &Session) -> Field {
+        temperature_field(preferences.unit)
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        let threshold = if field == 0 { preferences.temperature.0 } else { preferences.temperature.1 };
+        preferences.unit.to_degrees(threshold) as i32
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        let threshold = preferences.unit.from_degrees(value as i16);
+        if field == 0 {
+            preferences.temperature.0 = threshold;
+        } else {
+            preferences.temperature.1 = threshold;
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<11> = String::new();
+        uwrite!(&mut info_str, "{} - {}", self.value(0, preferences, session), self.value(1, preferences, session)).unwrap(); // Max str size 9
+        render_edit_screen(&info_str, field == 0, lcd);
+    }
+
+    fn on_confirm(&self, preferences: &mut Preferences, _session: &mut Session) -> bool {
+        // Check legality
+        if preferences.temperature.0 > preferences.temperature.1 {
+            preferences.temperature = (preferences.temperature.1, preferences.temperature.0);
+        }
+        true
+    }
+}
+
+/// Humidity and the range the sprinklers keep it in
+pub struct HumidityScreen;
+
+impl Screen for HumidityScreen {
+    fn render(&self, reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut data_str: String<12> = String::new();
+        uwrite!(&mut data_str, "RH: ").unwrap();
+        match reading {
+            Some(reading) => {
+                write_tenths(&mut data_str, reading.humidity as i32);
+                uwrite!(&mut data_str, "%").unwrap(); // Max str size 10
+            }
+            None => uwrite!(&mut data_str, "--").unwrap(), // Str size 6
+        }
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        uwrite!(&mut data_str, "({}%, {}%)", preferences.humidity.0, preferences.humidity.1).unwrap(); // Str size 12
+        render_screen(&data_str, false, lcd);
+    }
+
+    fn fields(&self) -> u8 {
+        2
+    }
+
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        HUMIDITY
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        if field == 0 { preferences.humidity.0 as i32 } else { preferences.humidity.1 as i32 }
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        if field == 0 {
+            preferences.humidity.0 = value as u8;
+        } else {
+            preferences.humidity.1 = value as u8;
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<11> = String::new();
+        uwrite!(&mut info_str, "{}% - {}%", preferences.humidity.0, preferences.humidity.1).unwrap(); // Max str size 11
+        render_edit_screen(&info_str, field == 0, lcd);
+    }
+
+    fn on_confirm(&self, preferences: &mut Preferences, _session: &mut Session) -> bool {
+        // Check legality
+        if preferences.humidity.0 > preferences.humidity.1 {
+            preferences.humidity = (preferences.humidity.1, preferences.humidity.0);
+        }
+        true
+    }
+}
+
+/// Air pressure, which has no configuration
+pub struct PressureScreen;
+
+impl Screen for PressureScreen {
+    fn render(&self, reading: Option<&Reading>, _outputs: &Outputs, _preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut data_str: String<16> = String::new();
+        uwrite!(&mut data_str, "PRS: ").unwrap();
+        match reading {
+            Some(reading) => {
+                // Pa to tenths of a millibar, rounded
+                write_tenths(&mut data_str, ((reading.pressure + 5) / 10) as i32);
+                uwrite!(&mut data_str, " mb").unwrap(); // Max str size 14
+            }
+            None => uwrite!(&mut data_str, "--").unwrap(), // Str size 7
+        }
+        render_screen(&data_str, true, lcd);
+    }
+}
+
+/// Gas resistance and the air quality index, with the levels that ventilate and alert
+pub struct GasScreen;
+
+/// The largest gas resistance shown in kOhm, in tenths
+const MAX_GAS_TENTHS: u32 = 999_999;
+
+/// Writes an air quality trigger level, or Off
+/// param out: The string to append to
+/// param level: The level, 0 if the trigger is off
+fn write_trigger<const N: usize>(out: &mut String<N>, level: u16) {
+    if level == 0 {
+        uwrite!(out, "Off").unwrap();
+    } else {
+        uwrite!(out, "{}", level).unwrap();
+    }
+}
+
+impl Screen for GasScreen {
+    fn render(&self, reading: Option<&Reading>, _outputs: &Outputs, _preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut data_str: String<16> = String::new();
+        let (gas, iaq) = reading.map_or((None, None), |reading| (reading.gas, reading.iaq));
+        match gas {
+            Some(gas) => {
+                uwrite!(&mut data_str, "Gas ").unwrap();
+                // Ohm to tenths of a kOhm, rounded, or of a MOhm once that doesn't fit
+                let kilo = gas.saturating_add(50) / 100;
+                if kilo <= MAX_GAS_TENTHS {
+                    write_tenths(&mut data_str, kilo as i32);
+                    uwrite!(&mut data_str, " kOhm").unwrap(); // Max str size 16
+                } else {
+                    write_tenths(&mut data_str, (gas.saturating_add(50_000) / 100_000) as i32);
+                    uwrite!(&mut data_str, " MOhm").unwrap(); // Max str size 15
+                }
+            }
+            None => uwrite!(&mut data_str, "Gas --").unwrap(), // Str size 6
+        }
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        match (gas, iaq) {
+            (_, Some(iaq)) => uwrite!(&mut data_str, "IAQ {} {}", iaq, air::label(iaq)).unwrap(), // Max str size 16
+            (Some(_), None) => uwrite!(&mut data_str, "IAQ calibrating").unwrap(), // Str size 15
+            (None, None) => {}
+        }
+        render_screen(&data_str, false, lcd);
+    }
+
+    /// Ventilate above, Alert above
+    fn fields(&self) -> u8 {
+        2
+    }
+
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        IAQ_TRIGGER
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        if field == 0 { preferences.iaq_vent as i32 } else { preferences.iaq_alert as i32 }
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        if field == 0 {
+            preferences.iaq_vent = value as u16;
+        } else {
+            preferences.iaq_alert = value as u16;
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<12> = String::new();
+        if field == 0 {
+            uwrite!(&mut info_str, "Vent: ").unwrap();
+            write_trigger(&mut info_str, preferences.iaq_vent); // Max str size 9
+        } else {
+            uwrite!(&mut info_str, "Alert: ").unwrap();
+            write_trigger(&mut info_str, preferences.iaq_alert); // Max str size 10
+        }
+        render_field_edit_screen(&info_str, lcd);
+    }
+}
+
+/// Time and date of the software clock
+/// The date is edited from the year down to the minute on a copy, which only sets the
+/// clock once it was confirmed; pressing UP and DOWN together cancels
+pub struct DateScreen;
+
+impl Screen for DateScreen {
+    fn render(&self, _reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let (time, date) = preferences.get_date_formatted();
+        render_screen(&time, true, lcd);
+        render_screen(&date, false, lcd);
+    }
+
+    /// Year, Month, Day, Hour, Minute, Confirmation
+    fn fields(&self) -> u8 {
+        6
+    }
+
+    fn on_select(&self, preferences: &mut Preferences, session: &mut Session) {
+        // The seconds can't be edited, the clock is set to the start of the minute
+        let date = preferences.date;
+        session.date_draft = (date - Duration::from_secs(date.second() as u64), true);
+        session.date_edited = false;
+    }
+
+    fn field(&self, field: u8, _preferences: &Preferences, session: &Session) -> Field {
+        match field {
+            0 => YEAR,
+            1 => MONTH,
+            2 => Field::new(1, session.date_draft.0.days_in_month() as i32).wrapping(),
+            3 => HOUR,
+            4 => MINUTE,
+            _ => YES_NO,
+        }
+    }
+
+    fn value(&self, field: u8, _preferences: &Preferences, session: &Session) -> i32 {
+        let (date, confirmed) = session.date_draft;
+        match field {
+            0 => date.year() as i32,
+            1 => date.month() as i32,
+            2 => date.day() as i32,
+            3 => date.hour() as i32,
+            4 => date.minute() as i32,
+            _ => confirmed as i32,
+        }
+    }
+
+    fn set_value(&self, field: u8, _preferences: &mut Preferences, session: &mut Session, value: i32) {
+        let (date, confirmed) = &mut session.date_draft;
+        let (mut year, mut month, mut day, mut hour, mut minute) = (date.year(), date.month(), date.day(), date.hour(), date.minute());
+        match field {
+            0 => year = value as u16,
+            1 => month = value as u8,
+            2 => day = value as u8,
+            3 => hour = value as u8,
+            4 => minute = value as u8,
+            _ => {
+                *confirmed = value != 0;
+                return;
+            }
+        }
+        session.date_edited = true;
+        // Moving from the 31st to a shorter month ends up on its last day
+        let day = day.min(days_in_month(month, year));
+        *date = DateTime::new(year, month, day, hour, minute, 0).unwrap_or(*date);
+    }
+
+    fn render_field(&self, field: u8, _preferences: &Preferences, session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<14> = String::new();
+        let (date, confirmed) = session.date_draft;
+        match field {
+            0 => uwrite!(&mut info_str, "Year: {}", date.year()).unwrap(), // Max str size 10
+            1 => uwrite!(&mut info_str, "Month: {}", date.month()).unwrap(), // Max str size 9
+            2 => uwrite!(&mut info_str, "Day: {}", date.day()).unwrap(), // Max str size 7
+            3 => uwrite!(&mut info_str, "Hour: {}", date.hour()).unwrap(), // Max str size 8
+            4 => uwrite!(&mut info_str, "Minute: {}", date.minute()).unwrap(), // Max str size 10
+            _ => uwrite!(&mut info_str, "Set clock: {}", if confirmed { "Yes" } else { "No" }).unwrap(), // Max str size 14
+        }
+        render_field_edit_screen(&info_str, lcd);
+    }
+
+    /// Starts the clock at the confirmed minute
+    /// An untouched draft is not applied, the clock kept running while the editor was open
+    fn on_confirm(&self, preferences: &mut Preferences, session: &mut Session) -> bool {
+        let (date, confirmed) = session.date_draft;
+        if !(confirmed && session.date_edited) {
+            return false;
+        }
+        preferences.date = date;
+        true
+    }
+
+    /// Leaves the clock as it is
+    fn on_chord(&self, _preferences: &mut Preferences, _session: &mut Session) -> Option<bool> {
+        Some(false)
+    }
+
+    fn sets_clock(&self) -> bool {
+        true
+    }
+}
+
+/// The watering schedules
+/// UP and DOWN page through the schedules before moving on to the neighbouring screens.
+/// The first field picks the schedule to edit or a new one, pressing UP and DOWN together
+/// while editing removes it
+pub struct WateringScreen;
+
+/// param preferences: Client Preferences
+/// param session: State of the menu
+/// returns the schedule picked on the watering screen, None for a new one
+fn selected_watering<'a>(preferences: &'a Preferences, session: &Session) -> Option<&'a Watering> {
+    preferences.watering.get(session.watering_slot as usize)
+}
+
+impl Screen for WateringScreen {
+    fn render(&self, _reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, session: &Session, lcd: &mut dyn Display) {
+        let Some(watering) = selected_watering(preferences, session) else {
+            render_screen("Watering: None", true, lcd);
+            return;
+        };
+        let mut data_str: String<16> = String::new();
+        uwrite!(&mut data_str, "{}/{} {}", session.watering_slot + 1, preferences.watering.len(), watering.format_time().as_str()).unwrap(); // Max str size 16
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        uwrite!(&mut data_str, "Days: {}", watering.format_days().as_str()).unwrap(); // Max str size 13
+        render_screen(&data_str, false, lcd);
+    }
+
+    fn on_browse(&self, next: bool, preferences: &Preferences, session: &mut Session) -> bool {
+        let last = preferences.watering.len().saturating_sub(1) as u8;
+        // A new schedule that was picked but never changed is not a page
+        let slot = session.watering_slot.min(last);
+        let page = match next {
+            true if slot < last => slot + 1,
+            false if slot > 0 => slot - 1,
+            _ => slot,
+        };
+        session.watering_slot = page;
+        page != slot
+    }
+
+    /// Schedule, Start hour, Start minute, Duration, then one field per weekday
+    fn fields(&self) -> u8 {
+        4 + DAY_NAMES.len() as u8
+    }
+
+    fn on_select(&self, preferences: &mut Preferences, session: &mut Session) {
+        session.watering_slot = session.watering_slot.min(preferences.watering.len().min(MAX_SCHEDULES - 1) as u8);
+    }
+
+    fn field(&self, field: u8, preferences: &Preferences, _session: &Session) -> Field {
+        match field {
+            // The schedule after the last one is a new one, while there is room
+            0 => Field::new(1, (preferences.watering.len() + 1).min(MAX_SCHEDULES) as i32).wrapping(),
+            1 => HOUR,
+            2 => MINUTE,
+            3 => WATERING_DURATION,
+            _ => YES_NO,
+        }
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, session: &Session) -> i32 {
+        let watering = selected_watering(preferences, session).copied().unwrap_or_default();
+        match field {
+            0 => session.watering_slot as i32 + 1,
+            1 => watering.start.0 as i32,
+            2 => watering.start.1 as i32,
+            3 => watering.duration as i32,
+            day => watering.runs_on(day - 4) as i32,
+        }
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, session: &mut Session, value: i32) {
+        if field == 0 {
+            session.watering_slot = value as u8 - 1;
+            return;
+        }
+        // A new schedule is created by the first change, at most one after the last
+        let slot = (session.watering_slot as usize).min(preferences.watering.len());
+        if slot == preferences.watering.len() && preferences.watering.push(Watering::default()).is_err() {
+            return;
+        }
+        session.watering_slot = slot as u8;
+        let watering = &mut preferences.watering[slot];
+        match field {
+            1 => watering.start.0 = value as u8,
+            2 => watering.start.1 = value as u8,
+            3 => watering.duration = value as u16,
+            day => watering.set_day(day - 4, value != 0),
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<14> = String::new();
+        let selected = selected_watering(preferences, session);
+        // A new schedule shows the values its first change starts from
+        let watering = selected.copied().unwrap_or_default();
+        match field {
+            0 if selected.is_none() => uwrite!(&mut info_str, "Schedule: New").unwrap(), // Max str size 13
+            0 => uwrite!(&mut info_str, "Schedule: {}", session.watering_slot + 1).unwrap(), // Max str size 11
+            1 => uwrite!(&mut info_str, "Hour: {}", watering.start.0).unwrap(), // Max str size 8
+            2 => uwrite!(&mut info_str, "Minute: {}", watering.start.1).unwrap(), // Max str size 10
+            3 => uwrite!(&mut info_str, "Duration: {}m", watering.duration).unwrap(), // Max str size 14
+            day => {
+                let on = if watering.runs_on(day - 4) { "Yes" } else { "No" };
+                uwrite!(&mut info_str, "{}: {}", DAY_NAMES[day as usize - 4], on).unwrap() // Max str size 8
+            }
+        }
+        render_field_edit_screen(&info_str, lcd);
+    }
+
+    fn on_chord(&self, preferences: &mut Preferences, session: &mut Session) -> Option<bool> {
+        if (session.watering_slot as usize) < preferences.watering.len() {
+            preferences.watering.remove(session.watering_slot as usize);
+        }
+        session.watering_slot = 0;
+        Some(true)
+    }
+}
+
+/// Writes the state of an actuator and the rule that decided it
+/// param out: The string to append to
+/// param name: Name of the actuator
+/// param decision: The outcome of the arbitration
+fn write_decision<const N: usize>(out: &mut String<N>, name: &str, decision: &Decision) {
+    uwrite!(out, "{} {}", name, if decision.on { "on" } else { "off" }).unwrap();
+    if let Some(reason) = decision.reason {
+        uwrite!(out, " {}", reason.label()).unwrap();
+    }
+}
+
+/// Gets the value of a manual override for editing
+/// param request: The override, None follows the rules
+fn override_value(request: Option<bool>) -> i32 {
+    match request {
+        None => 0,
+        Some(true) => 1,
+        Some(false) => 2,
+    }
+}
+
+/// The state of two actuators and why, with manual overrides
+pub struct OutputsScreen(pub [Output; 2]);
+
+impl Screen for OutputsScreen {
+    fn render(&self, _reading: Option<&Reading>, outputs: &Outputs, _preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut data_str: String<16> = String::new();
+        write_decision(&mut data_str, self.0[0].label(), &outputs.get(self.0[0])); // Max str size 16
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        write_decision(&mut data_str, self.0[1].label(), &outputs.get(self.0[1])); // Max str size 16
+        render_screen(&data_str, false, lcd);
+    }
+
+    /// One override per actuator
+    fn fields(&self) -> u8 {
+        2
+    }
+
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        OVERRIDE
+    }
+
+    fn value(&self, field: u8, _preferences: &Preferences, session: &Session) -> i32 {
+        override_value(session.overrides[self.0[field as usize] as usize])
+    }
+
+    fn set_value(&self, field: u8, _preferences: &mut Preferences, session: &mut Session, value: i32) {
+        let request = match value {
+            1 => Some(true),
+            2 => Some(false),
+            _ => None,
+        };
+        session.overrides[self.0[field as usize] as usize] = request;
+    }
+
+    fn render_field(&self, field: u8, _preferences: &Preferences, session: &Session, lcd: &mut dyn Display) {
+        let output = self.0[field as usize];
+        let mut info_str: String<11> = String::new();
+        let state = match session.overrides[output as usize] {
+            None => "Auto",
+            Some(true) => "On",
+            Some(false) => "Off",
+        };
+        uwrite!(&mut info_str, "{}: {}", output.label(), state).unwrap(); // Max str size 11
+        render_field_edit_screen(&info_str, lcd);
+    }
+}
+
+/// Writes a value with one decimal
+/// param out: The string to append to
+/// param tenths: The value in tenths
+fn write_tenths<const N: usize>(out: &mut String<N>, tenths: i32) {
+    if tenths < 0 {
+        out.push('-').unwrap();
+    }
+    let tenths = tenths.unsigned_abs();
+    uwrite!(out, "{}.{}", tenths / 10, tenths % 10).unwrap();
+}
+
+/// Raw measurements in metric units, for servicing the sensor
+pub struct DiagnosticsScreen;
+
+impl Screen for DiagnosticsScreen {
+    fn render(&self, reading: Option<&Reading>, _outputs: &Outputs, _preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let Some(reading) = reading else {
+            render_screen("--C --%", true, lcd);
+            render_screen("-- Pa", false, lcd);
+            return;
+        };
+        let mut data_str: String<16> = String::new();
+        write_tenths(&mut data_str, reading.temperature as i32);
+        uwrite!(&mut data_str, "C ").unwrap();
+        write_tenths(&mut data_str, reading.humidity as i32);
+        uwrite!(&mut data_str, "%").unwrap(); // Max str size 13
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        uwrite!(&mut data_str, "{} Pa", reading.pressure).unwrap(); // Max str size 9
+        render_screen(&data_str, false, lcd);
+    }
+}
+
+/// The unit temperatures are shown and edited in
+pub struct UnitScreen;
+
+impl Screen for UnitScreen {
+    fn render(&self, _reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        render_screen("Units", true, lcd);
+        render_screen(unit_name(preferences.unit), false, lcd);
+    }
+
+    fn fields(&self) -> u8 {
+        1
+    }
+
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        UNIT
+    }
+
+    fn value(&self, _field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        preferences.unit as i32
+    }
+
+    fn set_value(&self, _field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        preferences.unit = TemperatureUnit::from_u8(value as u8);
+    }
+
+    fn render_field(&self, _field: u8, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<16> = String::new();
+        uwrite!(&mut info_str, "Unit: {}", unit_name(preferences.unit)).unwrap(); // Max str size 16
+        render_field_edit_screen(&info_str, lcd);
+    }
+}
+
+/// returns the name of a temperature unit shown on the LCD
+fn unit_name(unit: TemperatureUnit) -> &'static str {
+    match unit {
+        TemperatureUnit::Fahrenheit => "Fahrenheit",
+        TemperatureUnit::Celsius => "Celsius",
+    }
+}
+
+/// The temperature above which the exhaust fan helps the open vent
+pub struct FanScreen;
+
+impl Screen for FanScreen {
+    fn render(&self, _reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        render_screen("Fan above", true, lcd);
+        let mut data_str: String<12> = String::new();
+        write_temperature(&mut data_str, preferences.fan_temperature, preferences.unit); // Max str size 4
+        render_screen(&data_str, false, lcd);
+    }
+
+    fn fields(&self) -> u8 {
+        1
+    }
+
+    fn field(&self, _field: u8, preferences: &Preferences, _session: &Session) -> Field {
+        temperature_field(preferences.unit)
+    }
+
+    fn value(&self, _field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        preferences.unit.to_degrees(preferences.fan_temperature) as i32
+    }
+
+    fn set_value(&self, _field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        preferences.fan_temperature = preferences.unit.from_degrees(value as i16);
+    }
+
+    fn render_field(&self, _field: u8, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<11> = String::new();
+        uwrite!(&mut info_str, "Fan: ").unwrap();
+        write_temperature(&mut info_str, preferences.fan_temperature, preferences.unit); // Max str size 9
+        render_field_edit_screen(&info_str, lcd);
+    }
+}
+
+/// Writes an opening in percent, or dashes if it is unknown
+/// param out: The string to append to
+/// param opening: The opening
+fn write_opening<const N: usize>(out: &mut String<N>, opening: Option<u8>) {
+    match opening {
+        Some(opening) => uwrite!(out, "{}%", opening).unwrap(),
+        None => uwrite!(out, "--").unwrap(),
+    }
+}
+
+/// The vent servo: control mode, proportional band and where the vent is
+pub struct VentScreen;
+
+impl Screen for VentScreen {
+    fn render(&self, _reading: Option<&Reading>, outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut data_str: String<16> = String::new();
+        if preferences.proportional_vent {
+            uwrite!(&mut data_str, "Vent prop. ").unwrap();
+            write_difference(&mut data_str, preferences.vent_band, preferences.unit); // Max str size 14
+        } else {
+            uwrite!(&mut data_str, "Vent on/off").unwrap(); // Str size 11
+        }
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        // Target and measured opening
+        uwrite!(&mut data_str, "Pos ").unwrap();
+        write_opening(&mut data_str, outputs.vent.map(|vent| vent.opening));
+        uwrite!(&mut data_str, " FB ").unwrap();
+        write_opening(&mut data_str, outputs.vent.and_then(|vent| vent.feedback)); // Max str size 15
+        render_screen(&data_str, false, lcd);
+    }
+
+    /// Mode, Proportional band
+    fn fields(&self) -> u8 {
+        2
+    }
+
+    fn field(&self, field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        if field == 0 { VENT_MODE } else { VENT_BAND }
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        if field == 0 { preferences.proportional_vent as i32 } else { preferences.unit.to_difference(preferences.vent_band) as i32 }
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        if field == 0 {
+            preferences.proportional_vent = value != 0;
+        } else {
+            preferences.vent_band = preferences.unit.from_difference(value as i16);
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<12> = String::new();
+        if field == 1 {
+            uwrite!(&mut info_str, "Band: ").unwrap();
+            write_difference(&mut info_str, preferences.vent_band, preferences.unit); // Max str size 9
+        } else if preferences.proportional_vent {
+            uwrite!(&mut info_str, "Mode: Prop.").unwrap(); // Str size 11
+        } else {
+            uwrite!(&mut info_str, "Mode: On/off").unwrap(); // Str size 12
+        }
+        render_field_edit_screen(&info_str, lcd);
+    }
+}
+
+/// Calibration of the servo positions of the closed and fully open vent
+/// The servo moves to the limit being edited, so it can be matched to the vent
+pub struct VentTravelScreen;
+
+impl Screen for VentTravelScreen {
+    fn render(&self, _reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        render_screen("Vent travel", true, lcd);
+        let mut data_str: String<12> = String::new();
+        uwrite!(&mut data_str, "{}% - {}%", preferences.vent_travel.0, preferences.vent_travel.1).unwrap(); // Max str size 11
+        render_screen(&data_str, false, lcd);
+    }
+
+    /// Closed, Open
+    fn fields(&self) -> u8 {
+        2
+    }
+
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        TRAVEL
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        if field == 0 { preferences.vent_travel.0 as i32 } else { preferences.vent_travel.1 as i32 }
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        if field == 0 {
+            preferences.vent_travel.0 = value as u8;
+        } else {
+            preferences.vent_travel.1 = value as u8;
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<13> = String::new();
+        if field == 0 {
+            uwrite!(&mut info_str, "Closed: {}%", preferences.vent_travel.0).unwrap(); // Max str size 12
+        } else {
+            uwrite!(&mut info_str, "Open: {}%", preferences.vent_travel.1).unwrap(); // Max str size 10
+        }
+        render_field_edit_screen(&info_str, lcd);
+    }
+
+    fn calibration_position(&self, field: u8, preferences: &Preferences) -> Option<u8> {
+        Some(if field == 0 { preferences.vent_travel.0 } else { preferences.vent_travel.1 })
+    }
+}
+
+/// Deadbands of the temperature and humidity thresholds
+pub struct DeadbandScreen;
+
+impl Screen for DeadbandScreen {
+    fn render(&self, _reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        render_screen("Deadband", true, lcd);
+        let mut data_str: String<12> = String::new();
+        write_difference(&mut data_str, preferences.deadband.0, preferences.unit);
+        uwrite!(&mut data_str, " {}%", preferences.deadband.1).unwrap(); // Max str size 7
+        render_screen(&data_str, false, lcd);
+    }
+
+    /// Temperature, Humidity
+    fn fields(&self) -> u8 {
+        2
+    }
+
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        DEADBAND
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        if field == 0 { preferences.unit.to_difference(preferences.deadband.0) as i32 } else { preferences.deadband.1 as i32 }
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        if field == 0 {
+            preferences.deadband.0 = preferences.unit.from_difference(value as i16);
+        } else {
+            preferences.deadband.1 = value as u8;
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<11> = String::new();
+        write_difference(&mut info_str, preferences.deadband.0, preferences.unit);
+        uwrite!(&mut info_str, " - {}%", preferences.deadband.1).unwrap(); // Max str size 9
+        render_edit_screen(&info_str, field == 0, lcd);
+    }
+}
+
+/// The PID controller: the actuator it drives, its output limits and the auto-tune
+/// Auto-tuning starts once editing is confirmed and stores the gains when it is done
+pub struct PidScreen;
+
+impl Screen for PidScreen {
+    fn render(&self, _reading: Option<&Reading>, outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut data_str: String<16> = String::new();
+        uwrite!(&mut data_str, "PID: {}", preferences.pid_mode.label()).unwrap(); // Max str size 11
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        match (outputs.tuning, outputs.pid) {
+            (Some(cycles), _) => uwrite!(&mut data_str, "Tuning {}/{}", cycles, TUNE_CYCLES).unwrap(), // Max str size 10
+            (None, Some(output)) => uwrite!(&mut data_str, "Out {}%", output).unwrap(), // Max str size 8
+            (None, None) => uwrite!(&mut data_str, "{}% - {}%", preferences.pid_limits.0, preferences.pid_limits.1).unwrap(), // Max str size 11
+        }
+        render_screen(&data_str, false, lcd);
+    }
+
+    /// Mode, Minimum output, Maximum output, Auto-tune
+    fn fields(&self) -> u8 {
+        4
+    }
+
+    fn field(&self, field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        match field {
+            0 => PID_MODE,
+            3 => YES_NO,
+            _ => PID_LIMIT,
+        }
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, session: &Session) -> i32 {
+        match field {
+            0 => preferences.pid_mode as i32,
+            1 => preferences.pid_limits.0 as i32,
+            2 => preferences.pid_limits.1 as i32,
+            _ => session.autotune as i32,
+        }
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, session: &mut Session, value: i32) {
+        match field {
+            0 => preferences.pid_mode = PidMode::from_u8(value as u8),
+            1 => preferences.pid_limits.0 = value as u8,
+            2 => preferences.pid_limits.1 = value as u8,
+            _ => session.autotune = value != 0,
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<12> = String::new();
+        match field {
+            0 => uwrite!(&mut info_str, "Mode: {}", preferences.pid_mode.label()).unwrap(), // Max str size 12
+            1 => uwrite!(&mut info_str, "Min: {}%", preferences.pid_limits.0).unwrap(), // Max str size 9
+            2 => uwrite!(&mut info_str, "Max: {}%", preferences.pid_limits.1).unwrap(), // Max str size 9
+            _ => uwrite!(&mut info_str, "Tune: {}", if session.autotune { "Yes" } else { "No" }).unwrap(), // Max str size 9
+        }
+        render_field_edit_screen(&info_str, lcd);
+    }
+
+    fn on_confirm(&self, preferences: &mut Preferences, session: &mut Session) -> bool {
+        // Check legality
+        if preferences.pid_limits.0 > preferences.pid_limits.1 {
+            preferences.pid_limits = (preferences.pid_limits.1, preferences.pid_limits.0);
+        }
+        // There is nothing to tune without an actuator
+        if preferences.pid_mode == PidMode::Off {
+            session.autotune = false;
+        }
+        true
+    }
+}
+
+/// Writes a value stored in hundredths with two decimals
+/// param out: The string to append to
+/// param value: The value in hundredths
+fn write_hundredths<const N: usize>(out: &mut String<N>, value: u16) {
+    let fraction = value % 100;
+    // ufmt has no zero padding
+    let padding = if fraction < 10 { "0" } else { "" };
+    uwrite!(out, "{}.{}{}", value / 100, padding, fraction).unwrap();
+}
+
+/// The gains of the PID controller, set by the auto-tune or by hand
+pub struct PidGainsScreen;
+
+impl Screen for PidGainsScreen {
+    fn render(&self, _reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let (kp, ki, kd) = preferences.pid_gains;
+        let mut data_str: String<16> = String::new();
+        uwrite!(&mut data_str, "Kp ").unwrap();
+        write_hundredths(&mut data_str, kp);
+        uwrite!(&mut data_str, " Ki ").unwrap();
+        write_hundredths(&mut data_str, ki); // Max str size 16
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        uwrite!(&mut data_str, "Kd ").unwrap();
+        write_hundredths(&mut data_str, kd); // Max str size 9
+        render_screen(&data_str, false, lcd);
+    }
+
+    /// Kp, Ki, Kd
+    fn fields(&self) -> u8 {
+        3
+    }
+
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        GAIN
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        let (kp, ki, kd) = preferences.pid_gains;
+        [kp, ki, kd][field as usize] as i32
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        let gains = &mut preferences.pid_gains;
+        match field {
+            0 => gains.0 = value as u16,
+            1 => gains.1 = value as u16,
+            _ => gains.2 = value as u16,
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, session: &Session, lcd: &mut dyn Display) {
+        let mut info_str: String<12> = String::new();
+        uwrite!(&mut info_str, "{}: ", ["Kp", "Ki", "Kd"][field as usize]).unwrap();
+        write_hundredths(&mut info_str, self.value(field, preferences, session) as u16); // Max str size 10
+        render_field_edit_screen(&info_str, lcd);
+    }
+}
+
+/// Minimum run and rest times of an actuator
+pub struct TimingScreen(pub Output);
+
+impl Screen for TimingScreen {
+    fn render(&self, _reading: Option<&Reading>, _outputs: &Outputs, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let mut data_str: String<12> = String::new();
+        uwrite!(&mut data_str, "{} on/off", self.0.label()).unwrap(); // Max str size 12
+        render_screen(&data_str, true, lcd);
+        data_str.clear();
+        let (on, off) = preferences.timing(self.0);
+        uwrite!(&mut data_str, "{}s / {}s", on, off).unwrap(); // Max str size 11
+        render_screen(&data_str, false, lcd);
+    }
+
+    /// Minimum run time, Minimum rest time
+    fn fields(&self) -> u8 {
+        2
+    }
+
+    fn field(&self, _field: u8, _preferences: &Preferences, _session: &Session) -> Field {
+        DURATION
+    }
+
+    fn value(&self, field: u8, preferences: &Preferences, _session: &Session) -> i32 {
+        let (on, off) = preferences.timing(self.0);
+        if field == 0 { on as i32 } else { off as i32 }
+    }
+
+    fn set_value(&self, field: u8, preferences: &mut Preferences, _session: &mut Session, value: i32) {
+        let timing = preferences.timing_mut(self.0);
+        if field == 0 {
+            timing.0 = value as u16;
+        } else {
+            timing.1 = value as u16;
+        }
+    }
+
+    fn render_field(&self, field: u8, preferences: &Preferences, _session: &Session, lcd: &mut dyn Display) {
+        let (on, off) = preferences.timing(self.0);
+        let mut info_str: String<13> = String::new();
+        uwrite!(&mut info_str, "{}s - {}s", on, off).unwrap(); // Max str size 11
+        render_edit_screen(&info_str, field == 0, lcd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::menu::{Edit, EditState, Input};
+
+    const COMMITTED: Edit = Edit::Done { committed: true };
+    const CANCELLED: Edit = Edit::Done { committed: false };
+
+    /// The Preferences and the state of the menu the screens work on
+    #[derive(Default)]
+    struct Menu {
+        preferences: Preferences,
+        session: Session,
+    }
+
+    impl Menu {
+        fn with(preferences: Preferences) -> Self {
+            Menu { preferences, session: Session::default() }
+        }
+
+        /// Opens the editor of a screen and feeds it a sequence of button presses
+        fn edit(&mut self, screen: &dyn Screen, inputs: &[Input]) -> Edit {
+            let mut edit = EditState::enter(screen, &mut self.preferences, &mut self.session).map_or(CANCELLED, Edit::Open);
+            for input in inputs {
+                if let Edit::Open(state) = edit {
+                    edit = self.press(screen, state, *input, false);
+                }
+            }
+            edit
+        }
+
+        /// Feeds a single button press to an open editor
+        fn press(&mut self, screen: &dyn Screen, state: EditState, input: Input, held: bool) -> Edit {
+            state.handle(screen, input, held, &mut self.preferences, &mut self.session)
+        }
+
+        /// Renders a screen while browsing
+        fn render(&self, screen: &dyn Screen, reading: Option<&Reading>, lcd: &mut MockLcd) {
+            screen.render(reading, &Outputs::default(), &self.preferences, &self.session, lcd);
+        }
+    }
+
+    /// The edit cursor, None once editing ended
+    fn open(edit: Edit) -> Option<EditState> {
+        match edit {
+            Edit::Open(state) => Some(state),
+            Edit::Done { .. } => None,
+        }
+    }
+
+    /// The field being edited
+    fn field(edit: Edit) -> Option<u8> {
+        open(edit).map(|state| state.field)
+    }
+
+    /// Keeps the text written to both lines
+    #[derive(Default)]
+    struct MockLcd {
+        lines: [String<16>; 2],
+        row: usize,
+    }
+
+    impl Display for MockLcd {
+        fn clean_display(&mut self) {
+            self.lines = Default::default();
+        }
+
+        fn set_cursor_pos(&mut self, pos: (u8, u8)) {
+            self.row = pos.1 as usize;
+        }
+
+        fn write_str_to_cur(&mut self, line: &str) {
+            self.lines[self.row].push_str(line).unwrap();
+        }
+
+        fn set_cursor_blink(&mut self, _on: bool) {}
+    }
+
+    #[test]
+    fn humidity_bounds_are_swapped_when_inverted() {
+        let mut menu = Menu::default();
+        let inputs = [Input::Up, Input::Up, Input::Select, Input::Down, Input::Down];
+        let state = menu.edit(&HumidityScreen, &inputs);
+        assert_eq!(field(state), Some(1));
+        assert_eq!(menu.preferences.humidity, (62, 68));
+
+        menu.preferences.humidity = (65, 64);
+        assert_eq!(menu.press(&HumidityScreen, open(state).unwrap(), Input::Select, false), COMMITTED);
+        assert_eq!(menu.preferences.humidity, (64, 65));
+    }
+
+    #[test]
+    fn date_fields_are_edited_in_turn() {
+        let mut menu = Menu::default();
+        // Year, Month, Day, Hour, Minute
+        let inputs = [Input::Up, Input::Select, Input::Down, Input::Select, Input::Select, Input::Up, Input::Select, Input::Down, Input::Select];
+        let state = menu.edit(&DateScreen, &inputs);
+        assert_eq!(field(state), Some(5));
+        assert_eq!(menu.preferences.date, DateTime::default());
+
+        assert_eq!(menu.press(&DateScreen, open(state).unwrap(), Input::Select, false), COMMITTED);
+        assert_eq!(menu.preferences.date, DateTime::new(2001, 12, 1, 1, 59, 0).unwrap());
+    }
+
+    #[test]
+    fn the_clock_only_changes_once_confirmed() {
+        let date = DateTime::new(2024, 6, 5, 10, 30, 45).unwrap();
+        let mut menu = Menu::with(Preferences { date, ..Preferences::default() });
+        let inputs = [Input::Up, Input::Select, Input::Select, Input::Select, Input::Select, Input::Select, Input::Down, Input::Select];
+        assert_eq!(menu.edit(&DateScreen, &inputs), CANCELLED);
+        assert_eq!(menu.preferences.date, date);
+
+        assert_eq!(menu.edit(&DateScreen, &[Input::Up, Input::UpDown]), CANCELLED);
+        assert_eq!(menu.preferences.date, date);
+
+        // Confirming without changes leaves the running clock alone
+        assert_eq!(menu.edit(&DateScreen, &[Input::Select; 6]), CANCELLED);
+        assert_eq!(menu.preferences.date, date);
+
+        // A change sets the clock to the start of the edited minute
+        assert_eq!(menu.edit(&DateScreen, &[Input::Select, Input::Select, Input::Select, Input::Up, Input::Select, Input::Select, Input::Select]), COMMITTED);
+        assert_eq!(menu.preferences.date, DateTime::new(2024, 6, 5, 11, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn watering_schedule_is_created_and_removed() {
+        let mut menu = Menu::default();
+        let state = menu.edit(&WateringScreen, &[Input::Select, Input::Up]);
+        assert_eq!(menu.preferences.watering.as_slice(), &[Watering { start: (7, 0), ..Watering::default() }]);
+
+        assert_eq!(menu.press(&WateringScreen, open(state).unwrap(), Input::UpDown, false), COMMITTED);
+        assert!(menu.preferences.watering.is_empty());
+    }
+
+    #[test]
+    fn a_new_schedule_shows_the_values_it_starts_from() {
+        let mut menu = Menu::default();
+        let mut lcd = MockLcd::default();
+        for (field, text) in [(0, "Schedule: New"), (1, "Hour: 6"), (2, "Minute: 0"), (3, "Duration: 10m"), (4, "Mon: Yes")] {
+            WateringScreen.render_field(field, &menu.preferences, &menu.session, &mut lcd);
+            assert_eq!(lcd.lines[0].as_str(), text);
+        }
+        assert!(menu.preferences.watering.is_empty());
+
+        // The first change continues from the value shown
+        let state = open(menu.edit(&WateringScreen, &[Input::Select, Input::Up])).unwrap();
+        state.render(&WateringScreen, &menu.preferences, &menu.session, &mut lcd);
+        assert_eq!(lcd.lines[0].as_str(), "Hour: 7");
+    }
+
+    #[test]
+    fn a_missing_slot_creates_one_schedule_with_the_pressed_value() {
+        let mut menu = Menu::default();
+        menu.session.watering_slot = 3;
+        WateringScreen.set_value(1, &mut menu.preferences, &mut menu.session, 9);
+        assert_eq!(menu.preferences.watering.as_slice(), &[Watering { start: (9, 0), ..Watering::default() }]);
+        assert_eq!(menu.session.watering_slot, 0);
+    }
+
+    #[test]
+    fn further_schedules_run_on_selected_weekdays() {
+        let mut menu = Menu::default();
+        menu.preferences.watering.push(Watering::default()).unwrap();
+        // Pick a new schedule and set it to 18:00 for 15 minutes, but not on Monday
+        let mut state = open(menu.edit(&WateringScreen, &[Input::Up, Input::Select, Input::Up]));
+        assert_eq!(menu.preferences.watering.len(), 2);
+        let inputs = [Input::Up; 11].iter().chain(&[Input::Select; 2]).chain(&[Input::Up; 5]).chain(&[Input::Select, Input::Down]);
+        for input in inputs {
+            state = state.and_then(|state| open(menu.press(&WateringScreen, state, *input, false)));
+        }
+        assert_eq!(state.map(|state| state.field), Some(4));
+        let mut lcd = MockLcd::default();
+        state.unwrap().render(&WateringScreen, &menu.preferences, &menu.session, &mut lcd);
+        assert_eq!(lcd.lines[0].as_str(), "Mon: No");
+
+        for _ in 0..7 {
+            state = state.and_then(|state| open(menu.press(&WateringScreen, state, Input::Select, false)));
+        }
+        assert_eq!(state, None);
+        assert_eq!(menu.preferences.watering[1], Watering { start: (18, 0), duration: 15, days: 0b111_1110 });
+        menu.render(&WateringScreen, None, &mut lcd);
+        assert_eq!(lcd.lines[0].as_str(), "2/2 18:00 15min");
+        assert_eq!(lcd.lines[1].as_str(), "Days: -TWTFSS");
+    }
+
+    #[test]
+    fn browsing_pages_through_the_schedules() {
+        let mut menu = Menu::default();
+        assert!(!WateringScreen.on_browse(true, &menu.preferences, &mut menu.session));
+        menu.preferences.watering.push(Watering::default()).unwrap();
+        menu.preferences.watering.push(Watering { start: (18, 0), ..Watering::default() }).unwrap();
+
+        assert!(WateringScreen.on_browse(true, &menu.preferences, &mut menu.session));
+        let mut lcd = MockLcd::default();
+        menu.render(&WateringScreen, None, &mut lcd);
+        assert!(lcd.lines[0].starts_with("2/2 18:"));
+        // The neighbouring screens are reached from the first and last schedule
+        assert!(!WateringScreen.on_browse(true, &menu.preferences, &mut menu.session));
+        assert!(WateringScreen.on_browse(false, &menu.preferences, &mut menu.session));
+        assert!(!WateringScreen.on_browse(false, &menu.preferences, &mut menu.session));
+        assert_eq!(menu.session.watering_slot, 0);
+    }
+
+    #[test]
+    fn screens_without_fields_are_not_edited() {
+        assert_eq!(EditState::enter(SCREENS[2], &mut Preferences::default(), &mut Session::default()), None);
+    }
+
+    #[test]
+    fn temperature_thresholds_can_be_raised() {
+        let mut menu = Menu::default();
+        menu.edit(&TemperatureScreen, &[Input::Up, Input::Up, Input::Select, Input::Down]);
+        // 62F and 79F
+        assert_eq!(menu.preferences.temperature, (167, 261));
+    }
+
+    #[test]
+    fn thresholds_are_edited_below_freezing_in_celsius() {
+        let mut menu = Menu::with(Preferences { unit: TemperatureUnit::Celsius, temperature: (20, 100), ..Preferences::default() });
+        menu.edit(&TemperatureScreen, &[Input::Down, Input::Down, Input::Down, Input::Select]);
+        assert_eq!(menu.preferences.temperature, (-10, 100));
+
+        let mut lcd = MockLcd::default();
+        menu.render(&TemperatureScreen, Some(&Reading { temperature: -52, ..Reading::default() }), &mut lcd);
+        assert_eq!(lcd.lines, ["Temp: -5.2C", "(-1, 10)"]);
+    }
+
+    #[test]
+    fn measurements_are_blank_until_the_sensor_answered() {
+        let mut lcd = MockLcd::default();
+        Menu::default().render(&TemperatureScreen, None, &mut lcd);
+        assert_eq!(lcd.lines, ["Temp: --", "(60, 80)"]);
+        Menu::default().render(&GasScreen, None, &mut lcd);
+        assert_eq!(lcd.lines, ["Gas --", ""]);
+    }
+
+    #[test]
+    fn gas_screen_shows_the_calibration() {
+        let mut lcd = MockLcd::default();
+        let reading = Reading { gas: Some(52_349), ..Reading::default() };
+        Menu::default().render(&GasScreen, Some(&reading), &mut lcd);
+        assert_eq!(lcd.lines, ["Gas 52.3 kOhm", "IAQ calibrating"]);
+        Menu::default().render(&GasScreen, Some(&Reading { iaq: Some(187), ..reading }), &mut lcd);
+        assert_eq!(lcd.lines[1], "IAQ 187 Moderate");
+    }
+
+    #[test]
+    fn large_gas_resistances_switch_to_megaohm() {
+        let mut lcd = MockLcd::default();
+        for (gas, line) in [(99_999_949, "Gas 99999.9 kOhm"), (99_999_950, "Gas 100.0 MOhm"), (u32::MAX, "Gas 4294.9 MOhm")] {
+            Menu::default().render(&GasScreen, Some(&Reading { gas: Some(gas), ..Reading::default() }), &mut lcd);
+            assert_eq!(lcd.lines[0], line);
+        }
+    }
+
+    #[test]
+    fn days_stay_within_the_month() {
+        let mut menu = Menu::with(Preferences { date: DateTime::new(2024, 1, 31, 0, 0, 0).unwrap(), ..Preferences::default() });
+        // 31 January becomes the last day of February
+        menu.edit(&DateScreen, &[Input::Select, Input::Up]);
+        assert_eq!(menu.session.date_draft.0.day(), 29);
+        menu.edit(&DateScreen, &[&[Input::Select, Input::Up][..], &[Input::Select; 5]].concat());
+        assert_eq!(menu.preferences.date, DateTime::new(2024, 2, 29, 0, 0, 0).unwrap());
+
+        // Leaving the leap year
+        menu.edit(&DateScreen, &[Input::Up]);
+        assert_eq!(menu.session.date_draft.0.day(), 28);
+
+        // Days wrap within the month
+        menu.edit(&DateScreen, &[Input::Select, Input::Select, Input::Up]);
+        assert_eq!(menu.session.date_draft.0.day(), 1);
+        menu.edit(&DateScreen, &[Input::Select, Input::Down, Input::Down]);
+        assert_eq!(menu.session.date_draft.0.month(), 12);
+    }
+
+    #[test]
+    fn holding_a_button_takes_bigger_steps() {
+        let mut menu = Menu::default();
+        let mut state = EditState::enter(&DateScreen, &mut menu.preferences, &mut menu.session);
+        for held in [false, true, true, true, true, true] {
+            state = state.and_then(|state| open(menu.press(&DateScreen, state, Input::Up, held)));
+        }
+        // 4 single steps, then 5 at a time
+        assert_eq!(menu.session.date_draft.0.year(), 2000 + 4 + 5 + 5);
+    }
+
+    #[test]
+    fn manual_overrides_cycle_through_auto_on_and_off() {
+        let mut menu = Menu::default();
+        let screen = OutputsScreen([Output::Heater, Output::ExhaustFan]);
+        menu.edit(&screen, &[Input::Up, Input::Select, Input::Down]);
+        assert_eq!(menu.session.overrides, [None, None, Some(true), Some(false)]);
+        menu.edit(&screen, &[Input::Up, Input::Up]);
+        assert_eq!(menu.session.overrides[Output::Heater as usize], None);
+    }
+
+    #[test]
+    fn actuator_timings_are_edited_separately() {
+        let mut menu = Menu::default();
+        menu.edit(&TimingScreen(Output::Sprinklers), &[Input::Down, Input::Select, Input::Up]);
+        assert_eq!(menu.preferences.sprinkler_timing, (25, 125));
+        assert_eq!(menu.preferences.vent_timing, Preferences::default().vent_timing);
+    }
+
+    #[test]
+    fn auto_tune_needs_a_pid_mode() {
+        let mut menu = Menu::default();
+        menu.edit(&PidScreen, &[Input::Select, Input::Select, Input::Select, Input::Up, Input::Select]);
+        assert!(!menu.session.autotune);
+
+        menu.edit(&PidScreen, &[Input::Up, Input::Select, Input::Select, Input::Select, Input::Up, Input::Select]);
+        assert_eq!(menu.preferences.pid_mode, PidMode::Heater);
+        assert!(menu.session.autotune);
+    }
+}
@@ -0,0 +1,498 @@
+//! Persistence of the Preferences
+//!
+//! Record layout: magic, schema version, payload length, payload, CRC-16 (little endian).
+//! Fields are only ever appended to the payload, so records written by older firmware
+//! are migrated by keeping the defaults for the fields they do not contain.
+//! Temperatures were stored in whole degrees Fahrenheit until version 6, and a single daily
+//! watering window until version 8; those bytes are still written so the layout stays the
+//! same, and are overwritten by the newer fields.
+
+use crate::arbiter::OUTPUTS;
+use crate::climate::{TemperatureUnit, Tenths};
+use crate::datetime::DateTime;
+use crate::field::Field;
+use crate::hal::Storage;
+use crate::pid::PidMode;
+use crate::schedule::{Watering, DAY_MINUTES, EVERY_DAY, MAX_DURATION, MAX_SCHEDULES};
+use crate::screens::{temperature_field, DEADBAND, DURATION, GAIN, HUMIDITY, IAQ_TRIGGER, PID_LIMIT, TRAVEL, VENT_BAND};
+use crate::Preferences;
+use heapless::Vec;
+
+/// Marks the EEPROM as written by this firmware
+const MAGIC: u8 = 0x47;
+/// Bump whenever fields are appended to the payload
+pub const SCHEMA_VERSION: u8 = 8;
+const HEADER_LEN: u16 = 3;
+const MAX_PAYLOAD: usize = 96;
+
+/// Calculates the CRC-16/CCITT-FALSE checksum
+/// param bytes: The data to check
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Reads the fields of a payload one after another
+/// Once the payload is exhausted every further read returns None
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Option<u8> {
+        let (first, rest) = self.bytes.split_first()?;
+        self.bytes = rest;
+        Some(*first)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let low = self.u8()?;
+        Some(u16::from_le_bytes([low, self.u8()?]))
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(self.u16()? as i16)
+    }
+
+    /// Reads a temperature in whole degrees Fahrenheit, as stored before version 6
+    fn fahrenheit(&mut self) -> Option<Tenths> {
+        Some(TemperatureUnit::Fahrenheit.from_degrees(self.u8()? as i16))
+    }
+
+    /// Reads a temperature difference in whole degrees Fahrenheit, as stored before version 6
+    fn fahrenheit_difference(&mut self) -> Option<Tenths> {
+        Some(TemperatureUnit::Fahrenheit.from_difference(self.u8()? as i16))
+    }
+}
+
+/// Converts a temperature to whole degrees Fahrenheit for the fields of older versions
+/// param tenths: Temperature in tenths of a degree Celsius
+fn fahrenheit(tenths: Tenths) -> u8 {
+    TemperatureUnit::Fahrenheit.to_degrees(tenths).clamp(0, u8::MAX as i16) as u8
+}
+
+/// Converts a temperature difference to whole degrees Fahrenheit for the fields of older versions
+/// param tenths: Difference in tenths of a degree Celsius
+fn fahrenheit_difference(tenths: Tenths) -> u8 {
+    TemperatureUnit::Fahrenheit.to_difference(tenths).clamp(0, u8::MAX as i16) as u8
+}
+
+/// Converts the first schedule to the daily window of older versions
+/// param watering: The schedule
+/// returns Start (Min, Hour), End (Min, Hour)
+fn window(watering: &Watering) -> (u8, u8, u8, u8) {
+    let end = (watering.start_minute() + watering.duration) % DAY_MINUTES;
+    (watering.start.1, watering.start.0, (end % 60) as u8, (end / 60) as u8)
+}
+
+/// Converts the daily window of older versions to a schedule
+/// An end before the start lies after midnight
+/// param window: Start (Min, Hour), End (Min, Hour)
+/// returns None if a time of the window is no time of day
+fn from_window(window: (u8, u8, u8, u8)) -> Option<Watering> {
+    if window.1 > 23 || window.0 > 59 || window.3 > 23 || window.2 > 59 {
+        return None;
+    }
+    let start = window.1 as u16 * 60 + window.0 as u16;
+    let end = window.3 as u16 * 60 + window.2 as u16;
+    let duration = (end + DAY_MINUTES - start) % DAY_MINUTES;
+    Some(Watering { start: (window.1, window.0), duration: duration.clamp(1, MAX_DURATION), days: EVERY_DAY })
+}
+
+/// Limits a stored schedule to what the watering editor allows
+/// param watering: The schedule as stored
+/// returns None if the start is no time of day
+fn checked(watering: Watering) -> Option<Watering> {
+    if watering.start.0 > 23 || watering.start.1 > 59 {
+        return None;
+    }
+    Some(Watering { duration: watering.duration.clamp(1, MAX_DURATION), days: watering.days & EVERY_DAY, ..watering })
+}
+
+/// Limits a stored value to the range of its field
+fn clamp<T: TryFrom<i32> + Into<i32> + Copy>(field: Field, value: T) -> T {
+    T::try_from(field.clamp(value.into())).unwrap_or(value)
+}
+
+/// Puts the bounds of a range back in order
+fn ordered<T: Ord + Copy>(range: (T, T)) -> (T, T) {
+    (range.0.min(range.1), range.0.max(range.1))
+}
+
+/// Brings the loaded values into the ranges of the fields they are edited with
+/// The CRC only proves that the record is intact, not that this firmware could have written it
+fn limit(preferences: &mut Preferences) {
+    let unit = preferences.unit;
+    let temperature = temperature_field(unit);
+    let degrees = |field: Field, tenths: Tenths| tenths.clamp(unit.from_degrees(field.min as i16), unit.from_degrees(field.max as i16));
+    let difference = |field: Field, tenths: Tenths| tenths.clamp(unit.from_difference(field.min as i16), unit.from_difference(field.max as i16));
+
+    preferences.temperature = ordered((degrees(temperature, preferences.temperature.0), degrees(temperature, preferences.temperature.1)));
+    preferences.humidity = ordered((clamp(HUMIDITY, preferences.humidity.0), clamp(HUMIDITY, preferences.humidity.1)));
+    preferences.deadband = (difference(DEADBAND, preferences.deadband.0), clamp(DEADBAND, preferences.deadband.1));
+    for output in OUTPUTS {
+        let timing = preferences.timing_mut(output);
+        *timing = (clamp(DURATION, timing.0), clamp(DURATION, timing.1));
+    }
+    preferences.fan_temperature = degrees(temperature, preferences.fan_temperature);
+    preferences.vent_travel = (clamp(TRAVEL, preferences.vent_travel.0), clamp(TRAVEL, preferences.vent_travel.1));
+    preferences.vent_band = difference(VENT_BAND, preferences.vent_band);
+    let gains = preferences.pid_gains;
+    preferences.pid_gains = (clamp(GAIN, gains.0), clamp(GAIN, gains.1), clamp(GAIN, gains.2));
+    preferences.pid_limits = ordered((clamp(PID_LIMIT, preferences.pid_limits.0), clamp(PID_LIMIT, preferences.pid_limits.1)));
+    preferences.iaq_vent = clamp(IAQ_TRIGGER, preferences.iaq_vent);
+    preferences.iaq_alert = clamp(IAQ_TRIGGER, preferences.iaq_alert);
+}
+
+/// Serializes the Preferences
+fn encode(preferences: &Preferences) -> Vec<u8, MAX_PAYLOAD> {
+    let mut payload: Vec<u8, MAX_PAYLOAD> = Vec::new();
+    let date = &preferences.date;
+    let (sec, min, hour, day, month, year) = (date.second(), date.minute(), date.hour(), date.day(), date.month(), date.year());
+    let watering = preferences.watering.first().map(window).unwrap_or_default();
+    let year = year.to_le_bytes();
+    // Version 1
+    payload
+        .extend_from_slice(&[
+            fahrenheit(preferences.temperature.0),
+            fahrenheit(preferences.temperature.1),
+            preferences.humidity.0,
+            preferences.humidity.1,
+            sec,
+            min,
+            hour,
+            day,
+            month,
+            year[0],
+            year[1],
+            !preferences.watering.is_empty() as u8,
+            watering.0,
+            watering.1,
+            watering.2,
+            watering.3,
+        ])
+        .unwrap();
+    // Version 2
+    payload.extend_from_slice(&[fahrenheit_difference(preferences.deadband.0), preferences.deadband.1]).unwrap();
+    for value in [preferences.vent_timing.0, preferences.vent_timing.1, preferences.sprinkler_timing.0, preferences.sprinkler_timing.1] {
+        payload.extend_from_slice(&value.to_le_bytes()).unwrap();
+    }
+    // Version 3
+    payload.push(fahrenheit(preferences.fan_temperature)).unwrap();
+    for value in [preferences.heater_timing.0, preferences.heater_timing.1, preferences.fan_timing.0, preferences.fan_timing.1] {
+        payload.extend_from_slice(&value.to_le_bytes()).unwrap();
+    }
+    // Version 4
+    payload
+        .extend_from_slice(&[
+            preferences.proportional_vent as u8,
+            preferences.vent_travel.0,
+            preferences.vent_travel.1,
+            fahrenheit_difference(preferences.vent_band),
+        ])
+        .unwrap();
+    // Version 5
+    payload.push(preferences.pid_mode as u8).unwrap();
+    for value in [preferences.pid_gains.0, preferences.pid_gains.1, preferences.pid_gains.2] {
+        payload.extend_from_slice(&value.to_le_bytes()).unwrap();
+    }
+    payload.extend_from_slice(&[preferences.pid_limits.0, preferences.pid_limits.1]).unwrap();
+    // Version 6
+    payload.push(preferences.unit as u8).unwrap();
+    for value in [
+        preferences.temperature.0,
+        preferences.temperature.1,
+        preferences.deadband.0,
+        preferences.fan_temperature,
+        preferences.vent_band,
+    ] {
+        payload.extend_from_slice(&value.to_le_bytes()).unwrap();
+    }
+    // Version 7
+    for value in [preferences.iaq_vent, preferences.iaq_alert] {
+        payload.extend_from_slice(&value.to_le_bytes()).unwrap();
+    }
+    // Version 8
+    payload.push(preferences.watering.len() as u8).unwrap();
+    for watering in &preferences.watering {
+        let duration = watering.duration.to_le_bytes();
+        payload.extend_from_slice(&[watering.start.0, watering.start.1, duration[0], duration[1], watering.days]).unwrap();
+    }
+    payload
+}
+
+/// Deserializes the Preferences
+/// Fields missing from the payload keep their default value, values outside the
+/// ranges of the editors are limited to them
+fn decode(payload: &[u8]) -> Preferences {
+    let mut preferences = Preferences::default();
+    decode_into(&mut Reader { bytes: payload }, &mut preferences);
+    limit(&mut preferences);
+    preferences
+}
+
+fn decode_into(reader: &mut Reader, preferences: &mut Preferences) -> Option<()> {
+    // Version 1
+    preferences.temperature = (reader.fahrenheit()?, reader.fahrenheit()?);
+    preferences.humidity = (reader.u8()?, reader.u8()?);
+    let (sec, min, hour, day, month, year) = (reader.u8()?, reader.u8()?, reader.u8()?, reader.u8()?, reader.u8()?, reader.u16()?);
+    // The clock starts over from the default if the stored date got corrupted
+    preferences.date = DateTime::new(year, month, day, hour, min, sec).unwrap_or_default();
+    let has_watering = reader.u8()? != 0;
+    let watering = (reader.u8()?, reader.u8()?, reader.u8()?, reader.u8()?);
+    preferences.watering.clear();
+    if let Some(watering) = from_window(watering).filter(|_| has_watering) {
+        preferences.watering.push(watering).unwrap();
+    }
+    // Version 2
+    preferences.deadband = (reader.fahrenheit_difference()?, reader.u8()?);
+    preferences.vent_timing = (reader.u16()?, reader.u16()?);
+    preferences.sprinkler_timing = (reader.u16()?, reader.u16()?);
+    // Version 3
+    preferences.fan_temperature = reader.fahrenheit()?;
+    preferences.heater_timing = (reader.u16()?, reader.u16()?);
+    preferences.fan_timing = (reader.u16()?, reader.u16()?);
+    // Version 4
+    preferences.proportional_vent = reader.u8()? != 0;
+    preferences.vent_travel = (reader.u8()?, reader.u8()?);
+    preferences.vent_band = reader.fahrenheit_difference()?;
+    // Version 5
+    preferences.pid_mode = PidMode::from_u8(reader.u8()?);
+    preferences.pid_gains = (reader.u16()?, reader.u16()?, reader.u16()?);
+    preferences.pid_limits = (reader.u8()?, reader.u8()?);
+    // Version 6
+    preferences.unit = TemperatureUnit::from_u8(reader.u8()?);
+    preferences.temperature = (reader.i16()?, reader.i16()?);
+    preferences.deadband.0 = reader.i16()?;
+    preferences.fan_temperature = reader.i16()?;
+    preferences.vent_band = reader.i16()?;
+    // Version 7
+    preferences.iaq_vent = reader.u16()?;
+    preferences.iaq_alert = reader.u16()?;
+    // Version 8
+    let count = reader.u8()?;
+    preferences.watering.clear();
+    for _ in 0..count.min(MAX_SCHEDULES as u8) {
+        let watering = Watering { start: (reader.u8()?, reader.u8()?), duration: reader.u16()?, days: reader.u8()? };
+        // Schedules the editor could not have created are dropped
+        if let Some(watering) = checked(watering) {
+            preferences.watering.push(watering).unwrap();
+        }
+    }
+    Some(())
+}
+
+impl Preferences {
+    /// Restores the Preferences saved with save()
+    /// Falls back to the defaults if nothing valid is stored or if the record
+    /// was written by a newer firmware
+    /// param storage: Non-volatile memory
+    pub fn load<S: Storage>(storage: &S) -> Self {
+        if storage.read_byte(0) != MAGIC {
+            return Preferences::default();
+        }
+        let version = storage.read_byte(1);
+        let len = storage.read_byte(2) as u16;
+        if version > SCHEMA_VERSION || len as usize > MAX_PAYLOAD {
+            return Preferences::default();
+        }
+
+        let mut record: Vec<u8, { MAX_PAYLOAD + 2 }> = Vec::new();
+        for address in 1..HEADER_LEN + len {
+            record.push(storage.read_byte(address)).unwrap();
+        }
+        let crc = u16::from_le_bytes([storage.read_byte(HEADER_LEN + len), storage.read_byte(HEADER_LEN + len + 1)]);
+        if crc16(&record) != crc {
+            return Preferences::default();
+        }
+        decode(&record[2..])
+    }
+
+    /// Saves the Preferences
+    /// Only bytes that differ from what is already stored are written to limit wear
+    /// param storage: Non-volatile memory
+    pub fn save<S: Storage>(&self, storage: &mut S) {
+        let payload = encode(self);
+        let mut record: Vec<u8, { MAX_PAYLOAD + 5 }> = Vec::new();
+        record.extend_from_slice(&[MAGIC, SCHEMA_VERSION, payload.len() as u8]).unwrap();
+        record.extend_from_slice(&payload).unwrap();
+        let crc = crc16(&record[1..]);
+        record.extend_from_slice(&crc.to_le_bytes()).unwrap();
+
+        for (address, byte) in record.iter().enumerate() {
+            if storage.read_byte(address as u16) != *byte {
+                storage.write_byte(address as u16, *byte);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::air::IAQ_MAX;
+    use crate::pid::MAX_GAIN;
+    use crate::schedule::Schedules;
+
+    struct MockEeprom {
+        bytes: [u8; 128],
+        writes: usize,
+    }
+
+    impl Default for MockEeprom {
+        fn default() -> Self {
+            // Erased EEPROM cells read as 0xFF
+            MockEeprom { bytes: [0xFF; 128], writes: 0 }
+        }
+    }
+
+    impl Storage for MockEeprom {
+        fn read_byte(&self, address: u16) -> u8 {
+            self.bytes[address as usize]
+        }
+
+        fn write_byte(&mut self, address: u16, value: u8) {
+            self.bytes[address as usize] = value;
+            self.writes += 1;
+        }
+    }
+
+    fn customized() -> Preferences {
+        Preferences {
+            temperature: (-25, 294),
+            humidity: (40, 75),
+            date: DateTime::new(2024, 7, 6, 5, 34, 12).unwrap(),
+            watering: Schedules::from_slice(&[
+                Watering { start: (6, 30), duration: 15, days: EVERY_DAY },
+                Watering { start: (18, 0), duration: 10, days: 0b001_0101 },
+            ])
+            .unwrap(),
+            deadband: (6, 5),
+            vent_timing: (300, 30),
+            sprinkler_timing: (10, 600),
+            fan_temperature: 321,
+            heater_timing: (0, 45),
+            fan_timing: (15, 15),
+            proportional_vent: true,
+            vent_travel: (85, 15),
+            vent_band: 56,
+            pid_mode: PidMode::Vent,
+            pid_gains: (3819, 381, 9547),
+            pid_limits: (10, 90),
+            unit: TemperatureUnit::Celsius,
+            iaq_vent: 150,
+            iaq_alert: 300,
+        }
+    }
+
+    #[test]
+    fn blank_eeprom_loads_defaults() {
+        assert_eq!(Preferences::load(&MockEeprom::default()), Preferences::default());
+    }
+
+    #[test]
+    fn saved_preferences_are_restored() {
+        let mut eeprom = MockEeprom::default();
+        customized().save(&mut eeprom);
+        assert_eq!(Preferences::load(&eeprom), customized());
+    }
+
+    #[test]
+    fn corrupted_records_load_defaults() {
+        let mut eeprom = MockEeprom::default();
+        customized().save(&mut eeprom);
+        eeprom.bytes[5] ^= 0x01;
+        assert_eq!(Preferences::load(&eeprom), Preferences::default());
+    }
+
+    #[test]
+    fn records_from_newer_firmware_load_defaults() {
+        let mut eeprom = MockEeprom::default();
+        customized().save(&mut eeprom);
+        eeprom.bytes[1] = SCHEMA_VERSION + 1;
+        assert_eq!(Preferences::load(&eeprom), Preferences::default());
+    }
+
+    #[test]
+    fn shorter_records_keep_defaults_for_missing_fields() {
+        let preferences = decode(&[50, 90, 30, 80]);
+        // Stored in Fahrenheit before version 6
+        assert_eq!(preferences.temperature, (100, 322));
+        assert_eq!(preferences.humidity, (30, 80));
+        assert_eq!(preferences.date, Preferences::default().date);
+    }
+
+    #[test]
+    fn daily_watering_windows_become_schedules() {
+        let mut payload = [0; 16];
+        // 06:30 - 06:45, as stored before version 8
+        payload[11..].copy_from_slice(&[1, 30, 6, 45, 6]);
+        let watering = decode(&payload).watering;
+        assert_eq!(watering.as_slice(), &[Watering { start: (6, 30), duration: 15, days: EVERY_DAY }]);
+        assert_eq!(window(&watering[0]), (30, 6, 45, 6));
+        // 23:00 - 01:00
+        assert_eq!(from_window((0, 23, 0, 1)).unwrap().duration, 120);
+        assert_eq!(from_window((0, 25, 0, 1)), None);
+    }
+
+    #[test]
+    fn out_of_range_values_are_limited() {
+        let mut preferences = customized();
+        preferences.temperature = (i16::MAX, i16::MIN);
+        preferences.humidity = (250, 101);
+        preferences.deadband = (-10, 200);
+        preferences.vent_timing = (901, u16::MAX);
+        preferences.sprinkler_timing = (1000, 0);
+        preferences.heater_timing = (0, 2000);
+        preferences.fan_timing = (950, 5);
+        preferences.fan_temperature = -3000;
+        preferences.vent_band = 0;
+        preferences.iaq_vent = 501;
+        preferences.iaq_alert = u16::MAX;
+        preferences.vent_travel = (120, 101);
+        preferences.pid_gains = (MAX_GAIN + 1, 5, u16::MAX);
+        preferences.pid_limits = (200, 40);
+        preferences.watering[0].duration = MAX_DURATION + 60;
+        preferences.watering[0].days = 0xFF;
+        preferences.watering[1].start = (24, 0);
+        let mut eeprom = MockEeprom::default();
+        preferences.save(&mut eeprom);
+
+        let loaded = Preferences::load(&eeprom);
+        // Inverted ranges are put back in order, as the editors do
+        assert_eq!(loaded.temperature, (-300, 500));
+        assert_eq!(loaded.humidity, (100, 100));
+        assert_eq!(loaded.deadband, (0, 10));
+        assert_eq!((loaded.vent_timing, loaded.sprinkler_timing), ((900, 900), (900, 0)));
+        assert_eq!((loaded.heater_timing, loaded.fan_timing), ((0, 900), (900, 5)));
+        assert_eq!((loaded.fan_temperature, loaded.vent_band), (-300, 10));
+        assert_eq!((loaded.iaq_vent, loaded.iaq_alert), (IAQ_MAX, IAQ_MAX));
+        assert_eq!(loaded.vent_travel, (100, 100));
+        assert_eq!(loaded.pid_gains, (MAX_GAIN, 5, MAX_GAIN));
+        assert_eq!(loaded.pid_limits, (40, 100));
+        assert_eq!(loaded.watering.as_slice(), &[Watering { start: (6, 30), duration: MAX_DURATION, days: EVERY_DAY }]);
+    }
+
+    #[test]
+    fn only_changed_bytes_are_written() {
+        let mut eeprom = MockEeprom::default();
+        let mut preferences = customized();
+        preferences.save(&mut eeprom);
+
+        eeprom.writes = 0;
+        preferences.save(&mut eeprom);
+        assert_eq!(eeprom.writes, 0);
+
+        preferences.humidity.1 = 76;
+        preferences.save(&mut eeprom);
+        // The humidity byte and both CRC bytes
+        assert!(eeprom.writes <= 3);
+        assert_eq!(Preferences::load(&eeprom), preferences);
+    }
+}
@@ -0,0 +1,74 @@
+//! Proportional roof vent
+//!
+//! With a servo (or an H-bridge driven by a PWM duty) the roof vent can be held anywhere
+//! between closed and fully open. In proportional mode the opening grows with the
+//! temperature above the upper threshold and reaches fully open at the threshold plus
+//! the proportional band. Travel limits map the opening onto the range of the drive, so
+//! a servo that only needs part of its range, or is mounted the other way round, works too.
+
+use crate::climate::Tenths;
+
+/// Where the vent was sent and where it actually is
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct VentPosition {
+    /// Target opening in percent
+    pub opening: u8,
+    /// Measured opening in percent, None without position feedback
+    pub feedback: Option<u8>,
+}
+
+/// Gets the opening for a temperature
+/// param temperature: Temperature in tenths of a degree Celsius
+/// param threshold: The temperature above which the vent starts to open
+/// param band: Distance above the threshold at which the vent is fully open
+/// returns the opening in percent
+pub fn opening(temperature: Tenths, threshold: Tenths, band: Tenths) -> u8 {
+    let above = (temperature as i32 - threshold as i32).max(0);
+    (above * 100 / band.max(1) as i32).min(100) as u8
+}
+
+/// Converts an opening into a position of the drive
+/// param opening: Opening in percent
+/// param travel: Drive positions (percent of the drive range) when Closed and fully Open
+/// returns the position of the drive in percent of its range
+pub fn to_drive(opening: u8, travel: (u8, u8)) -> u8 {
+    let span = travel.1 as i16 - travel.0 as i16;
+    (travel.0 as i16 + span * opening.min(100) as i16 / 100) as u8
+}
+
+/// Converts a position of the drive back into an opening
+/// param position: Position of the drive in percent of its range
+/// param travel: Drive positions when Closed and fully Open
+/// returns the opening in percent, None if the travel limits are equal
+pub fn from_drive(position: u8, travel: (u8, u8)) -> Option<u8> {
+    let span = travel.1 as i16 - travel.0 as i16;
+    if span == 0 {
+        return None;
+    }
+    Some(((position as i16 - travel.0 as i16) * 100 / span).clamp(0, 100) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opening_grows_across_the_band() {
+        assert_eq!(opening(300, 300, 60), 0);
+        assert_eq!(opening(330, 300, 60), 50);
+        assert_eq!(opening(400, 300, 60), 100);
+        assert_eq!(opening(301, 300, 0), 100);
+        assert_eq!(opening(-50, 300, 60), 0);
+    }
+
+    #[test]
+    fn travel_limits_map_the_opening() {
+        assert_eq!(to_drive(50, (20, 80)), 50);
+        assert_eq!(to_drive(0, (20, 80)), 20);
+        // Servo mounted the other way round
+        assert_eq!(to_drive(25, (90, 10)), 70);
+        assert_eq!(from_drive(70, (90, 10)), Some(25));
+        assert_eq!(from_drive(5, (20, 80)), Some(0));
+        assert_eq!(from_drive(50, (40, 40)), None);
+    }
+}
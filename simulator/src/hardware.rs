@@ -0,0 +1,459 @@
+use crate::plant::{Outputs, Plant};
+use embedded_hal::blocking::delay::DelayMs;
+use greenhouse_core::climate::{ClimateData, Reading};
+use greenhouse_core::hal::{Actuator, Button, ClimateSensor, Display, Monotonic, RealTimeClock, Servo, SmokeSensor, Storage};
+use greenhouse_core::datetime::DateTime;
+use std::cell::{Cell, RefCell};
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+/// Simulated time a button stays held after the controller noticed it
+const BUTTON_HOLD_MS: u64 = 100;
+/// Real time between two terminal redraws
+const FRAME_TIME: Duration = Duration::from_millis(100);
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// A button that is held down until the controller has seen it
+#[derive(Default)]
+pub struct ButtonState {
+    pressed_at: Cell<Option<u64>>,
+    observed: Cell<bool>,
+}
+
+impl ButtonState {
+    fn press(&self, now: u64) {
+        self.pressed_at.set(Some(now));
+        self.observed.set(false);
+    }
+
+    fn release_if_seen(&self, now: u64) {
+        if let Some(pressed_at) = self.pressed_at.get() {
+            if self.observed.get() && now - pressed_at >= BUTTON_HOLD_MS {
+                self.pressed_at.set(None);
+            }
+        }
+    }
+}
+
+/// The 16x2 character LCD
+pub struct Screen {
+    cells: [[u8; 16]; 2],
+    cursor: (u8, u8),
+    blink: bool,
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen { cells: [[b' '; 16]; 2], cursor: (0, 0), blink: false }
+    }
+}
+
+/// Everything shared between the simulated hardware
+pub struct World {
+    pub millis: Cell<u64>,
+    pub plant: RefCell<Plant>,
+    pub smoke: Cell<bool>,
+    /// If the climate sensor stopped answering
+    pub sensor_failed: Cell<bool>,
+    pub buzzer: Cell<bool>,
+    pub sprinklers: Cell<bool>,
+    pub roof_vent: Cell<bool>,
+    pub heater: Cell<bool>,
+    pub exhaust_fan: Cell<bool>,
+    /// Position of the vent servo in percent, None without one
+    pub vent_servo: Cell<Option<u8>>,
+    /// The last measurement handed to the controller, as the controller sees it
+    pub reading: Cell<Option<Reading>>,
+    pub up: ButtonState,
+    pub down: ButtonState,
+    pub select: ButtonState,
+    pub screen: RefCell<Screen>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World {
+            millis: Cell::new(0),
+            plant: RefCell::new(Plant::default()),
+            smoke: Cell::new(false),
+            sensor_failed: Cell::new(false),
+            buzzer: Cell::new(false),
+            sprinklers: Cell::new(false),
+            roof_vent: Cell::new(false),
+            heater: Cell::new(false),
+            exhaust_fan: Cell::new(false),
+            vent_servo: Cell::new(None),
+            reading: Cell::new(None),
+            up: ButtonState::default(),
+            down: ButtonState::default(),
+            select: ButtonState::default(),
+            screen: RefCell::new(Screen::default()),
+        }
+    }
+}
+
+impl World {
+    /// Hours since midnight of the simulated day
+    fn hours(&self) -> f32 {
+        (self.millis.get() % DAY_MS) as f32 / 3_600_000.
+    }
+}
+
+pub enum ButtonId {
+    Up,
+    Down,
+    Select,
+}
+
+pub struct SimButton {
+    pub world: Rc<World>,
+    pub id: ButtonId,
+}
+
+impl Button for SimButton {
+    fn is_pressed(&self) -> bool {
+        let state = match self.id {
+            ButtonId::Up => &self.world.up,
+            ButtonId::Down => &self.world.down,
+            ButtonId::Select => &self.world.select,
+        };
+        let pressed = state.pressed_at.get().is_some();
+        if pressed {
+            state.observed.set(true);
+        }
+        pressed
+    }
+}
+
+pub enum Output {
+    Buzzer,
+    Sprinklers,
+    RoofVent,
+    Heater,
+    ExhaustFan,
+}
+
+pub struct SimActuator {
+    pub world: Rc<World>,
+    pub output: Output,
+}
+
+impl SimActuator {
+    fn state(&self) -> &Cell<bool> {
+        match self.output {
+            Output::Buzzer => &self.world.buzzer,
+            Output::Sprinklers => &self.world.sprinklers,
+            Output::RoofVent => &self.world.roof_vent,
+            Output::Heater => &self.world.heater,
+            Output::ExhaustFan => &self.world.exhaust_fan,
+        }
+    }
+}
+
+impl Actuator for SimActuator {
+    fn set_on(&mut self, on: bool) {
+        self.state().set(on);
+    }
+
+    fn is_on(&self) -> bool {
+        self.state().get()
+    }
+}
+
+/// A vent servo that reaches its position instantly, the travel is the full servo range
+pub struct SimServo(pub Rc<World>);
+
+impl Servo for SimServo {
+    fn set_position(&mut self, position: u8) {
+        self.0.vent_servo.set(Some(position));
+    }
+
+    fn feedback(&mut self) -> Option<u8> {
+        self.0.vent_servo.get()
+    }
+}
+
+pub struct SimSmokeDetector(pub Rc<World>);
+
+impl SmokeSensor for SimSmokeDetector {
+    fn is_smoke_detected(&self) -> bool {
+        self.0.smoke.get()
+    }
+}
+
+/// A BME680 reading the simulated plant
+pub struct SimSensor(pub Rc<World>);
+
+impl ClimateSensor for SimSensor {
+    type Error = ();
+
+    fn prepare(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn measure(&mut self) -> Result<ClimateData, ()> {
+        if self.0.sensor_failed.get() {
+            return Err(());
+        }
+        let plant = self.0.plant.borrow();
+        // The model settles on exact values, while a real sensor never reads the same twice;
+        // up to 0.1hPa of noise keeps the controller from taking it for a stuck sensor
+        let noise = (self.0.millis.get() / 1000 * 7919 % 21) as f32 / 100. - 0.1;
+        let data = ClimateData {
+            temperature_celsius: plant.temperature,
+            humidity_percent: plant.humidity,
+            pressure_hpa: plant.pressure + noise,
+            gas_resistance_ohm: Some(plant.gas_resistance()),
+        };
+        self.0.reading.set(Some(Reading::from(&data)));
+        Ok(data)
+    }
+}
+
+pub struct SimLcd(pub Rc<World>);
+
+impl Display for SimLcd {
+    fn clean_display(&mut self) {
+        let mut screen = self.0.screen.borrow_mut();
+        screen.cells = [[b' '; 16]; 2];
+        screen.cursor = (0, 0);
+    }
+
+    fn set_cursor_pos(&mut self, pos: (u8, u8)) {
+        self.0.screen.borrow_mut().cursor = pos;
+    }
+
+    fn write_str_to_cur(&mut self, line: &str) {
+        let mut screen = self.0.screen.borrow_mut();
+        for byte in line.bytes() {
+            let (column, row) = screen.cursor;
+            if column < 16 && row < 2 {
+                screen.cells[row as usize][column as usize] = byte;
+            }
+            screen.cursor.0 = column.saturating_add(1);
+        }
+    }
+
+    fn set_cursor_blink(&mut self, on: bool) {
+        self.0.screen.borrow_mut().blink = on;
+    }
+}
+
+/// The EEPROM, which is lost when the simulator exits
+pub struct SimEeprom(pub [u8; 1024]);
+
+impl Default for SimEeprom {
+    fn default() -> Self {
+        // Erased EEPROM cells read as 0xFF
+        SimEeprom([0xFF; 1024])
+    }
+}
+
+impl Storage for SimEeprom {
+    fn read_byte(&self, address: u16) -> u8 {
+        self.0[address as usize]
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.0[address as usize] = value;
+    }
+}
+
+/// A DS3231 keeping time with the simulation
+pub struct SimRtc {
+    world: Rc<World>,
+    date: DateTime,
+    /// Simulated time at which date was correct
+    date_at: u64,
+}
+
+impl SimRtc {
+    pub fn new(world: Rc<World>) -> Self {
+        SimRtc { world, date: DateTime::default(), date_at: 0 }
+    }
+}
+
+impl RealTimeClock for SimRtc {
+    type Error = ();
+
+    fn get_date(&mut self) -> Result<DateTime, ()> {
+        let seconds = (self.world.millis.get() - self.date_at) / 1000;
+        self.date = self.date + Duration::from_secs(seconds);
+        self.date_at += seconds * 1000;
+        Ok(self.date)
+    }
+
+    fn set_date(&mut self, date: DateTime) -> Result<(), ()> {
+        self.date = date;
+        self.date_at = self.world.millis.get();
+        Ok(())
+    }
+}
+
+/// The millisecond counter, following simulated time
+pub struct SimTimer(pub Rc<World>);
+
+impl Monotonic for SimTimer {
+    fn millis(&self) -> u32 {
+        self.0.millis.get() as u32
+    }
+}
+
+/// Advances the simulation instead of waiting
+/// This is where keyboard input, the plant model and the terminal are updated
+pub struct SimDelay {
+    pub world: Rc<World>,
+    pub keys: Receiver<u8>,
+    /// Simulated milliseconds per real millisecond
+    pub speed: u64,
+    /// Stop after this many simulated days
+    pub days: Option<u64>,
+    pub started: Instant,
+    pub last_frame: Option<Instant>,
+}
+
+impl SimDelay {
+    fn handle_keys(&self) {
+        let now = self.world.millis.get();
+        while let Ok(key) = self.keys.try_recv() {
+            match key {
+                b'u' => self.world.up.press(now),
+                b'd' => self.world.down.press(now),
+                b's' => self.world.select.press(now),
+                b'b' => {
+                    self.world.up.press(now);
+                    self.world.down.press(now);
+                }
+                b'f' => self.world.smoke.set(!self.world.smoke.get()),
+                b'x' => self.world.sensor_failed.set(!self.world.sensor_failed.get()),
+                b'q' => self.quit(),
+                _ => {}
+            }
+        }
+    }
+
+    fn quit(&self) -> ! {
+        draw(&self.world);
+        std::process::exit(0);
+    }
+
+    fn pace(&mut self) {
+        let now = self.world.millis.get();
+        let target = self.started + Duration::from_millis(now / self.speed);
+        let real = Instant::now();
+        if target > real {
+            std::thread::sleep(target - real);
+        }
+
+        let real = Instant::now();
+        let frame_due = match self.last_frame {
+            Some(last) => real - last >= FRAME_TIME,
+            None => true,
+        };
+        if frame_due {
+            draw(&self.world);
+            self.last_frame = Some(real);
+        }
+    }
+}
+
+impl DelayMs<u16> for SimDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.handle_keys();
+
+        let now = self.world.millis.get() + ms as u64;
+        self.world.millis.set(now);
+        let hours = self.world.hours();
+        let world = &self.world;
+        let outputs = Outputs {
+            vent_opening: match world.vent_servo.get() {
+                Some(position) => position as f32 / 100.,
+                None => if world.roof_vent.get() { 1. } else { 0. },
+            },
+            sprinklers_on: world.sprinklers.get(),
+            heater_on: world.heater.get(),
+            fan_on: world.exhaust_fan.get(),
+        };
+        world.plant.borrow_mut().step(ms as f32 / 1000., hours, outputs);
+
+        self.world.up.release_if_seen(now);
+        self.world.down.release_if_seen(now);
+        self.world.select.release_if_seen(now);
+
+        if self.days.is_some_and(|days| now >= days * DAY_MS) {
+            self.quit();
+        }
+        self.pace();
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+/// Formats a fixed-point value with one decimal
+/// param tenths: The value in tenths
+fn tenths(tenths: i32) -> String {
+    let sign = if tenths < 0 { "-" } else { "" };
+    format!("{}{}.{}", sign, tenths.unsigned_abs() / 10, tenths.unsigned_abs() % 10)
+}
+
+/// Renders the LCD and the state of the simulation onto the terminal
+fn draw(world: &World) {
+    let screen = world.screen.borrow();
+    let plant = world.plant.borrow();
+    let millis = world.millis.get();
+    let blink = screen.blink && (millis / 500).is_multiple_of(2);
+
+    let mut out = String::from("\x1b[2J\x1b[H+----------------+\n");
+    for (row, cells) in screen.cells.iter().enumerate() {
+        out.push('|');
+        for (column, cell) in cells.iter().enumerate() {
+            if blink && screen.cursor == (column as u8, row as u8) {
+                out.push('_');
+            } else {
+                out.push(*cell as char);
+            }
+        }
+        out.push_str("|\n");
+    }
+    out.push_str("+----------------+\n\n");
+
+    let minutes = millis / 60_000;
+    out.push_str(&format!(
+        "Day {} {:02}:{:02}   Outside {:.1}C   Inside {:.1}C {:.0}%RH\n",
+        millis / DAY_MS + 1,
+        minutes / 60 % 24,
+        minutes % 60,
+        Plant::outside_temperature(world.hours()),
+        plant.temperature,
+        plant.humidity,
+    ));
+    if let Some(reading) = world.reading.get() {
+        out.push_str(&format!(
+            "Sensor {}C {}%RH {}hPa {}kOhm\n",
+            tenths(reading.temperature as i32),
+            tenths(reading.humidity as i32),
+            tenths((reading.pressure / 10) as i32),
+            tenths(reading.gas.map_or(0, |gas| gas / 100) as i32),
+        ));
+    }
+    out.push_str(&format!(
+        "Vent: {}{}   Fan: {}   Heater: {}   Sprinklers: {}   Buzzer: {}   Smoke: {}   Sensor: {}\n\n",
+        on_off(world.roof_vent.get()),
+        world.vent_servo.get().map(|position| format!(" ({}%)", position)).unwrap_or_default(),
+        on_off(world.exhaust_fan.get()),
+        on_off(world.heater.get()),
+        on_off(world.sprinklers.get()),
+        on_off(world.buzzer.get()),
+        on_off(world.smoke.get()),
+        if world.sensor_failed.get() { "FAILED" } else { "OK" },
+    ));
+    out.push_str("Keys (then Enter): u=up d=down s=select b=up+down f=toggle smoke x=toggle sensor failure q=quit\n");
+
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout.write_all(out.as_bytes());
+    let _ = stdout.flush();
+}
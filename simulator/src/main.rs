@@ -0,0 +1,93 @@
+//! Desktop simulator for the greenhouse monitor.
+//!
+//! Runs the same controller as the firmware against a simulated greenhouse,
+//! so thresholds and watering schedules can be tuned over days in seconds.
+
+mod hardware;
+mod plant;
+
+use greenhouse_core::controller::{Greenhouse, Hardware};
+use hardware::{ButtonId, Output, SimActuator, SimButton, SimDelay, SimEeprom, SimLcd, SimRtc, SimSensor, SimServo, SimSmokeDetector, SimTimer, World};
+use std::io::Read;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: greenhouse-sim [--speed <simulated seconds per second>] [--days <days to simulate>] [--no-rtc] [--servo]";
+
+struct Options {
+    speed: u64,
+    days: Option<u64>,
+    rtc: bool,
+    servo: bool,
+}
+
+impl Options {
+    /// Parses the command line arguments
+    /// returns None if they are invalid
+    fn parse(mut args: impl Iterator<Item = String>) -> Option<Options> {
+        let mut options = Options { speed: 60, days: None, rtc: true, servo: false };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--speed" => options.speed = args.next()?.parse().ok().filter(|speed| *speed > 0)?,
+                "--days" => options.days = Some(args.next()?.parse().ok()?),
+                "--no-rtc" => options.rtc = false,
+                "--servo" => options.servo = true,
+                _ => return None,
+            }
+        }
+        Some(options)
+    }
+}
+
+fn main() {
+    let Some(options) = Options::parse(std::env::args().skip(1)) else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+
+    // Forward key presses from the terminal
+    let (sender, keys) = mpsc::channel();
+    std::thread::spawn(move || {
+        for byte in std::io::stdin().lock().bytes() {
+            let Ok(byte) = byte else { break };
+            if sender.send(byte.to_ascii_lowercase()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let world = Rc::new(World::default());
+    let rtc = options.rtc.then(|| SimRtc::new(world.clone()));
+    let vent_servo = options.servo.then(|| SimServo(world.clone()));
+    let hardware = Hardware {
+        up_button: SimButton { world: world.clone(), id: ButtonId::Up },
+        down_button: SimButton { world: world.clone(), id: ButtonId::Down },
+        select_button: SimButton { world: world.clone(), id: ButtonId::Select },
+        smoke_detector: SimSmokeDetector(world.clone()),
+        buzzer: SimActuator { world: world.clone(), output: Output::Buzzer },
+        sprinklers: SimActuator { world: world.clone(), output: Output::Sprinklers },
+        roof_vent: SimActuator { world: world.clone(), output: Output::RoofVent },
+        heater: SimActuator { world: world.clone(), output: Output::Heater },
+        exhaust_fan: SimActuator { world: world.clone(), output: Output::ExhaustFan },
+        sensor: SimSensor(world.clone()),
+        lcd: SimLcd(world.clone()),
+        delay: SimDelay {
+            world: world.clone(),
+            keys,
+            speed: options.speed,
+            days: options.days,
+            started: Instant::now(),
+            last_frame: None,
+        },
+        storage: SimEeprom::default(),
+        rtc,
+        timer: SimTimer(world),
+        vent_servo,
+    };
+    let mut greenhouse = Greenhouse::new(hardware);
+
+    loop {
+        greenhouse.tick();
+    }
+}
@@ -0,0 +1,101 @@
+use std::f32::consts::PI;
+
+/// Average outside temperature in Celsius
+const OUTSIDE_MEAN: f32 = 17.;
+/// Difference between the average and the warmest/coldest outside temperature
+const OUTSIDE_SWING: f32 = 8.;
+/// Outside relative humidity in percent
+const OUTSIDE_HUMIDITY: f32 = 60.;
+/// Heating by the sun at noon in Celsius per hour
+const SOLAR_GAIN: f32 = 8.;
+/// Humidity added by the plants in percent per hour
+const TRANSPIRATION: f32 = 10.;
+/// Humidity added by the sprinklers in percent per hour
+const SPRINKLER_GAIN: f32 = 180.;
+/// Evaporative cooling by the sprinklers in Celsius per hour
+const SPRINKLER_COOLING: f32 = 1.5;
+/// Share of the inside air exchanged with the outside per hour
+const EXCHANGE_CLOSED: f32 = 1.;
+const EXCHANGE_OPEN: f32 = 6.;
+/// Additional exchange by the exhaust fan
+const EXCHANGE_FAN: f32 = 12.;
+/// Heating by the heater in Celsius per hour
+const HEATER_GAIN: f32 = 10.;
+/// Volatile compounds given off by plants and soil, relative to outside air per hour
+const EMISSION: f32 = 0.5;
+/// Gas resistance of the BME680 in outside air in Ohm
+const CLEAN_GAS_OHM: f32 = 50_000.;
+
+/// The state of the actuators acting on the greenhouse
+pub struct Outputs {
+    /// 0 (closed) to 1 (fully open)
+    pub vent_opening: f32,
+    pub sprinklers_on: bool,
+    pub heater_on: bool,
+    pub fan_on: bool,
+}
+
+/// A very simple thermal and humidity model of the greenhouse
+pub struct Plant {
+    pub temperature: f32,
+    pub humidity: f32,
+    pub pressure: f32,
+    /// Volatile compounds in the air, 0 is as clean as outside
+    pub pollution: f32,
+}
+
+impl Default for Plant {
+    fn default() -> Self {
+        Plant {
+            temperature: OUTSIDE_MEAN,
+            humidity: OUTSIDE_HUMIDITY,
+            pressure: 1013.25,
+            pollution: 0.,
+        }
+    }
+}
+
+impl Plant {
+    /// Outside temperature, coldest at 03:00 and warmest at 15:00
+    /// param hours: Hours since midnight
+    pub fn outside_temperature(hours: f32) -> f32 {
+        OUTSIDE_MEAN + OUTSIDE_SWING * (2. * PI * (hours - 15.) / 24.).cos()
+    }
+
+    /// Strength of the sunlight between 0 (night) and 1 (noon)
+    /// param hours: Hours since midnight
+    pub fn sunlight(hours: f32) -> f32 {
+        (PI * (hours - 6.) / 12.).sin().max(0.)
+    }
+
+    /// Gas resistance the BME680 measures, lower the more polluted the air is
+    pub fn gas_resistance(&self) -> u32 {
+        (CLEAN_GAS_OHM / (1. + self.pollution)) as u32
+    }
+
+    /// Advances the model
+    /// param seconds: Simulated time that has passed
+    /// param hours: Hours since midnight
+    /// param outputs: The state of the actuators
+    pub fn step(&mut self, seconds: f32, hours: f32, outputs: Outputs) {
+        let dt = seconds / 3600.;
+        let mut exchange = EXCHANGE_CLOSED + (EXCHANGE_OPEN - EXCHANGE_CLOSED) * outputs.vent_opening;
+        if outputs.fan_on {
+            exchange += EXCHANGE_FAN;
+        }
+
+        let mut heating = (Self::outside_temperature(hours) - self.temperature) * exchange + SOLAR_GAIN * Self::sunlight(hours);
+        let mut moistening = (OUTSIDE_HUMIDITY - self.humidity) * exchange + TRANSPIRATION;
+        if outputs.heater_on {
+            heating += HEATER_GAIN;
+        }
+        if outputs.sprinklers_on {
+            heating -= SPRINKLER_COOLING;
+            moistening += SPRINKLER_GAIN;
+        }
+
+        self.temperature += heating * dt;
+        self.humidity = (self.humidity + moistening * dt).clamp(0., 100.);
+        self.pollution = (self.pollution + (EMISSION - self.pollution * exchange) * dt).max(0.);
+    }
+}